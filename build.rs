@@ -2,6 +2,8 @@ use std::process::Command;
 
 fn main() {
     println!("cargo:rerun-if-changed=.git/HEAD");
+    // Embedded by `sqlx::migrate!`
+    println!("cargo:rerun-if-changed=migrations");
     if let Some(rev) = rev_parse() {
         println!("cargo:rustc-env=RUSTBOT_REV={}", rev);
     }
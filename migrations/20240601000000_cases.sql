@@ -0,0 +1,13 @@
+-- Moderation actions taken against members, shown by `?cases`
+CREATE TABLE cases (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    moderator_id INTEGER NOT NULL,
+    reason TEXT,
+    -- Unix timestamp in seconds
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX cases_by_user ON cases (guild_id, user_id);
@@ -0,0 +1,2 @@
+-- Length of temporary actions like timeouts, in seconds
+ALTER TABLE cases ADD COLUMN duration INTEGER;
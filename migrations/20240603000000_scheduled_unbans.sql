@@ -0,0 +1,8 @@
+-- Unbans scheduled by `?tempban`, executed by the background scheduler
+CREATE TABLE scheduled_unbans (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    -- Unix timestamp in seconds
+    unban_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
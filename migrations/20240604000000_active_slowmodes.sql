@@ -0,0 +1,13 @@
+-- Slowmodes applied by `?slowmode`, lifted by the background scheduler
+CREATE TABLE active_slowmodes (
+    channel_id INTEGER PRIMARY KEY,
+    previous_slowmode_rate INTEGER NOT NULL,
+    -- In minutes
+    duration INTEGER NOT NULL,
+    -- In seconds
+    rate INTEGER NOT NULL,
+    -- Unix timestamp in milliseconds
+    invocation_time INTEGER NOT NULL,
+    -- Unix timestamp in seconds
+    expires_at INTEGER NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Automod word filters, managed with `?filter`
+CREATE TABLE filters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    pattern TEXT NOT NULL,
+    action TEXT NOT NULL,
+    created_by INTEGER NOT NULL,
+    -- Unix timestamp in seconds
+    created_at INTEGER NOT NULL
+);
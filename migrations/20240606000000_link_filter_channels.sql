@@ -0,0 +1,5 @@
+-- Channels in which the automod link filter was turned off with `?linkfilter disable`
+CREATE TABLE link_filter_disabled_channels (
+    channel_id INTEGER PRIMARY KEY NOT NULL,
+    guild_id INTEGER NOT NULL
+);
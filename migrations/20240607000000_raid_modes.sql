@@ -0,0 +1,8 @@
+-- Guilds currently in raid mode. Slowmodes applied by raid mode are stored in `active_slowmodes`
+-- with `started_at` as their invocation time
+CREATE TABLE raid_modes (
+    guild_id INTEGER PRIMARY KEY NOT NULL,
+    previous_verification_level INTEGER NOT NULL,
+    -- Unix timestamp in milliseconds
+    started_at INTEGER NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Channels locked down by `?lockdown`, unlocked by `?unlock` or the background scheduler
+CREATE TABLE active_lockdowns (
+    channel_id INTEGER PRIMARY KEY NOT NULL,
+    guild_id INTEGER NOT NULL,
+    -- Permission bits of the @everyone overwrite before the lockdown, NULL if there was none
+    previous_allow INTEGER,
+    previous_deny INTEGER,
+    -- Unix timestamp in milliseconds
+    invocation_time INTEGER NOT NULL,
+    -- Unix timestamp in seconds, NULL for lockdowns lasting until `?unlock`
+    expires_at INTEGER
+);
@@ -0,0 +1,14 @@
+-- Reports sent with `/report` or the Report message context menu command
+CREATE TABLE reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    reporter_id INTEGER NOT NULL,
+    -- Private thread in the reports channel, NULL until it has been created
+    thread_id INTEGER,
+    status TEXT NOT NULL,
+    -- Moderator who claimed or closed the report
+    handled_by INTEGER,
+    -- Unix timestamps in seconds
+    created_at INTEGER NOT NULL,
+    closed_at INTEGER
+);
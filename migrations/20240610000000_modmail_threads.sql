@@ -0,0 +1,12 @@
+-- Modmail conversations, each relayed between a member's DMs and a private staff thread
+CREATE TABLE modmail_threads (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    thread_id INTEGER NOT NULL,
+    -- Unix timestamps in seconds
+    opened_at INTEGER NOT NULL,
+    closed_at INTEGER
+);
+
+CREATE INDEX modmail_threads_by_user ON modmail_threads (user_id, closed_at);
+CREATE INDEX modmail_threads_by_thread ON modmail_threads (thread_id);
@@ -0,0 +1,16 @@
+-- Who may use which commands, managed with `?permissions`. Targets are lowercased qualified command
+-- names like `filter add`, or categories like `category:moderation`
+CREATE TABLE command_permissions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    target TEXT NOT NULL,
+    -- Exactly one of role_id and permissions is set
+    role_id INTEGER,
+    -- Discord permission bits, all of which are required
+    permissions INTEGER,
+    created_by INTEGER NOT NULL,
+    -- Unix timestamp in seconds
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX command_permissions_by_guild ON command_permissions (guild_id);
@@ -0,0 +1,12 @@
+-- Settings of each guild, managed with `?settings`. In the guild configured as DISCORD_SERVER_ID,
+-- unset columns fall back to the config file. In all other guilds, they are disabled
+CREATE TABLE guild_settings (
+    guild_id INTEGER PRIMARY KEY NOT NULL,
+    mod_role_id INTEGER,
+    reports_channel INTEGER,
+    mod_log_channel INTEGER,
+    message_log_channel INTEGER,
+    member_log_channel INTEGER,
+    -- Lowercased names of disabled command categories, separated by commas
+    disabled_categories TEXT NOT NULL DEFAULT ''
+);
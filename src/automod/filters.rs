@@ -0,0 +1,315 @@
+use anyhow::Error;
+use poise::{serenity_prelude as serenity, ChoiceParameter as _};
+
+use super::Offense;
+use crate::moderation::{record_case, CaseKind, ModLogEntry};
+use crate::types::{Context, Data};
+
+/// What happens to a message matching a filter. Ordered by severity, so if multiple filters match,
+/// the most severe action is taken
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, sqlx::Type, poise::ChoiceParameter,
+)]
+#[sqlx(rename_all = "snake_case")]
+pub enum FilterAction {
+    /// Only post the message to the reports channel for moderators to review
+    #[name = "report"]
+    Report,
+    #[name = "delete"]
+    Delete,
+    /// Delete the message and record a warning
+    #[name = "warn"]
+    Warn,
+    /// Delete the message and time out the author
+    #[name = "timeout"]
+    Timeout,
+}
+
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub id: i64,
+    pub guild_id: serenity::GuildId,
+    pub regex: regex::Regex,
+    pub action: FilterAction,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct FilterRow {
+    id: i64,
+    guild_id: i64,
+    pattern: String,
+    action: FilterAction,
+}
+
+/// Filters match case-insensitively
+fn compile_pattern(pattern: &str) -> Result<regex::Regex, regex::Error> {
+    regex::RegexBuilder::new(pattern).case_insensitive(true).size_limit(1 << 20).build()
+}
+
+/// Loads and compiles all filters from the database
+pub async fn load_filters(database: &sqlx::SqlitePool) -> Result<Vec<Filter>, Error> {
+    let rows: Vec<FilterRow> = sqlx::query_as("SELECT id, guild_id, pattern, action FROM filters")
+        .fetch_all(database)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| match compile_pattern(&row.pattern) {
+            Ok(regex) => Some(Filter {
+                id: row.id,
+                guild_id: serenity::GuildId::new(row.guild_id as u64),
+                regex,
+                action: row.action,
+            }),
+            Err(e) => {
+                log::warn!("Skipping filter #{} with invalid pattern: {}", row.id, e);
+                None
+            },
+        })
+        .collect())
+}
+
+async fn reload_filters(data: &Data) -> Result<(), Error> {
+    let filters = load_filters(&data.database).await?;
+    *data.automod.filters.write().unwrap() = filters;
+    Ok(())
+}
+
+/// Returns the ID and action of the most severe filter matching the content
+fn find_matching_filter(
+    data: &Data,
+    guild_id: serenity::GuildId,
+    content: &str,
+) -> Option<(i64, FilterAction)> {
+    data.automod
+        .filters
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|filter| filter.guild_id == guild_id && filter.regex.is_match(content))
+        .map(|filter| (filter.id, filter.action))
+        .max_by_key(|&(_, action)| action)
+}
+
+/// Checks a new or edited message against the filters and takes the configured action. Returns
+/// whether the message was deleted
+pub(super) async fn apply_filters(
+    ctx: &serenity::Context,
+    data: &Data,
+    offense: &Offense<'_>,
+) -> Result<bool, Error> {
+    let Some((filter_id, action)) = find_matching_filter(data, offense.guild_id, offense.content)
+    else {
+        return Ok(false);
+    };
+    log::info!("Automod: message by {} matched filter #{}", offense.author_id, filter_id);
+
+    let reason = format!("Message matched filter #{}", filter_id);
+    match action {
+        FilterAction::Report => {
+            report_message(ctx, data, offense, filter_id).await?;
+            return Ok(false);
+        },
+        FilterAction::Delete => {
+            super::delete_messages(ctx, &[(offense.channel_id, offense.message_id)]).await;
+            ModLogEntry::new("Automod filtered message", data.bot_user_id)
+                .target(offense.author_id)
+                .channel(offense.channel_id)
+                .field("Filter", format!("#{}", filter_id))
+                .field("Content", offense.content)
+                .post_in_guild(ctx, data, offense.guild_id)
+                .await;
+        },
+        FilterAction::Warn => {
+            super::delete_messages(ctx, &[(offense.channel_id, offense.message_id)]).await;
+            let reason = format!("Automod: {}", reason);
+            let case_id = record_case(
+                &data.database,
+                offense.guild_id,
+                CaseKind::Warn,
+                offense.author_id,
+                data.bot_user_id,
+                Some(&reason),
+                None,
+            )
+            .await?;
+
+            ModLogEntry::new("Automod warning", data.bot_user_id)
+                .target(offense.author_id)
+                .channel(offense.channel_id)
+                .case(case_id)
+                .field("Content", offense.content)
+                .reason(Some(&reason))
+                .post_in_guild(ctx, data, offense.guild_id)
+                .await;
+        },
+        FilterAction::Timeout => {
+            super::delete_messages(ctx, &[(offense.channel_id, offense.message_id)]).await;
+            super::timeout_member(
+                ctx,
+                data,
+                offense,
+                &reason,
+                data.config.get().automod.filter_timeout,
+            )
+            .await?;
+        },
+    }
+
+    Ok(true)
+}
+
+/// Posts the message in the reports channel so moderators can review borderline content
+async fn report_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    offense: &Offense<'_>,
+    filter_id: i64,
+) -> Result<(), Error> {
+    let Some(reports_channel) = data.settings(offense.guild_id).reports_channel else {
+        log::warn!("Can't report message matching filter #{}: no reports channel", filter_id);
+        return Ok(());
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Message matched filter #{}", filter_id))
+        .description(format!(
+            "Message by <@{}> in <#{}>\nhttps://discord.com/channels/{}/{}/{}",
+            offense.author_id,
+            offense.channel_id,
+            offense.guild_id,
+            offense.channel_id,
+            offense.message_id
+        ))
+        .field("Content", crate::helpers::truncate_for_field(offense.content), false)
+        .timestamp(serenity::Timestamp::now())
+        .color(crate::types::EMBED_COLOR);
+    reports_channel.send_message(ctx, serenity::CreateMessage::new().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Manages the automod word filters (moderator only)
+///
+/// Filters are regular expressions matched case-insensitively against every new or edited \
+/// message. Possible actions on match:
+/// - `report`: post the message to the reports channel for review
+/// - `delete`: delete the message
+/// - `warn`: delete the message and record a warning
+/// - `timeout`: delete the message and time out the author
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    hide_in_help,
+    category = "Moderation",
+    subcommands("filter_add", "filter_remove", "filter_list"),
+    subcommand_required
+)]
+pub async fn filter(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Adds a filter
+///
+/// ?filter add <action> <pattern>
+#[poise::command(prefix_command, slash_command, guild_only, rename = "add")]
+pub async fn filter_add(
+    ctx: Context<'_>,
+    #[description = "What to do with matching messages"] action: FilterAction,
+    #[description = "Regular expression to match, case-insensitive"]
+    #[rest]
+    pattern: String,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    if let Err(e) = compile_pattern(&pattern) {
+        anyhow::bail!("Invalid pattern: {}", e);
+    }
+
+    let filter_id: i64 = sqlx::query_scalar(
+        "INSERT INTO filters (guild_id, pattern, action, created_by, created_at)
+         VALUES (?, ?, ?, ?, ?)
+         RETURNING id",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(&pattern)
+    .bind(action)
+    .bind(ctx.author().id.get() as i64)
+    .bind(chrono::Utc::now().timestamp())
+    .fetch_one(&ctx.data().database)
+    .await?;
+    reload_filters(ctx.data()).await?;
+
+    ModLogEntry::from_context(ctx, "Filter added")
+        .field("Filter", format!("#{}", filter_id))
+        .field("Action", action.name())
+        .field("Pattern", format!("`{}`", pattern))
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(format!("Added filter #{} (`{}`): `{}`", filter_id, action.name(), pattern)).await?;
+
+    Ok(())
+}
+
+/// Removes a filter
+///
+/// ?filter remove <id>
+#[poise::command(prefix_command, slash_command, guild_only, rename = "remove")]
+pub async fn filter_remove(
+    ctx: Context<'_>,
+    #[description = "ID of the filter, as shown by `?filter list`"] filter_id: i64,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    let pattern: Option<String> =
+        sqlx::query_scalar("DELETE FROM filters WHERE id = ? AND guild_id = ? RETURNING pattern")
+            .bind(filter_id)
+            .bind(guild_id.get() as i64)
+            .fetch_optional(&ctx.data().database)
+            .await?;
+    let pattern = pattern.ok_or(anyhow::anyhow!("There is no filter #{}", filter_id))?;
+    reload_filters(ctx.data()).await?;
+
+    ModLogEntry::from_context(ctx, "Filter removed")
+        .field("Filter", format!("#{}", filter_id))
+        .field("Pattern", format!("`{}`", pattern))
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(format!("Removed filter #{}: `{}`", filter_id, pattern)).await?;
+
+    Ok(())
+}
+
+/// Lists all filters
+#[poise::command(prefix_command, slash_command, guild_only, rename = "list")]
+pub async fn filter_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    let rows: Vec<FilterRow> = sqlx::query_as(
+        "SELECT id, guild_id, pattern, action FROM filters WHERE guild_id = ? ORDER BY id",
+    )
+    .bind(guild_id.get() as i64)
+    .fetch_all(&ctx.data().database)
+    .await?;
+
+    if rows.is_empty() {
+        ctx.say("No filters configured").await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for row in rows {
+        list += &format!("#{} `{}`: `{}`\n", row.id, row.action.name(), row.pattern);
+    }
+
+    crate::helpers::reply_potentially_long_text(ctx, &list, "", async {
+        "\n(list was truncated)".into()
+    })
+    .await
+}
@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+
+use anyhow::Error;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude as serenity;
+
+use super::Offense;
+use crate::moderation::ModLogEntry;
+use crate::types::{Context, Data};
+
+static INVITE_REGEX: Lazy<regex::Regex> = Lazy::new(|| {
+    regex::Regex::new(r"(?i)(?:discord(?:app)?\.com/invite|discord\.gg)/([a-z0-9-]+)").unwrap()
+});
+static URL_REGEX: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r"(?i)\bhttps?://([^\s/?#<>]+)").unwrap());
+
+/// How many resolved invite codes are remembered before the cache is cleared
+const MAX_CACHED_INVITES: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LinkViolation {
+    ForeignInvite,
+    BlockedDomain(String),
+}
+
+impl std::fmt::Display for LinkViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ForeignInvite => f.write_str("Invite link to another server"),
+            Self::BlockedDomain(domain) => write!(f, "Link to blocked domain `{}`", domain),
+        }
+    }
+}
+
+/// Loads the phishing domain blocklist: one domain per line, empty lines and lines starting with
+/// `#` are ignored
+pub fn load_blocked_domains(path: &str) -> Result<HashSet<String>, Error> {
+    let blocklist = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Couldn't read domain blocklist {}: {}", path, e))?;
+
+    Ok(blocklist
+        .lines()
+        .map(|line| line.trim().trim_end_matches('.').to_lowercase())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect())
+}
+
+/// Loads the channels in which the link filter was disabled
+pub async fn load_link_filter_disabled_channels(
+    database: &sqlx::SqlitePool,
+) -> Result<HashSet<serenity::ChannelId>, Error> {
+    let channel_ids: Vec<i64> =
+        sqlx::query_scalar("SELECT channel_id FROM link_filter_disabled_channels")
+            .fetch_all(database)
+            .await?;
+
+    Ok(channel_ids.into_iter().map(|id| serenity::ChannelId::new(id as u64)).collect())
+}
+
+/// Extracts the lowercased host from the authority part of a URL, e.g. `user@Example.com:8080`
+fn host_of(authority: &str) -> String {
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let host = match host.strip_prefix('[') {
+        // IPv6 addresses like `[::1]:8080` contain colons themselves
+        Some(address) => address.split(']').next().unwrap_or(address),
+        None => host.split(':').next().unwrap_or(host),
+    };
+    host.trim_end_matches('.').to_lowercase()
+}
+
+/// Subdomains of blocked domains are blocked too
+fn is_blocked(blocked_domains: &HashSet<String>, host: &str) -> bool {
+    let mut domain = host;
+    loop {
+        if blocked_domains.contains(domain) {
+            return true;
+        }
+        match domain.split_once('.') {
+            Some((_, parent)) => domain = parent,
+            None => return false,
+        }
+    }
+}
+
+/// Resolves the guild an invite code leads to, `Some(None)` for unknown invites and invites to
+/// group DMs. Returns `None` if the invite couldn't be resolved, for example because of a rate
+/// limit, so that messages aren't removed just because Discord is unavailable
+async fn invite_guild(
+    ctx: &serenity::Context,
+    data: &Data,
+    code: &str,
+) -> Option<Option<serenity::GuildId>> {
+    if let Some(guild_id) = data.automod.invite_guilds.lock().unwrap().get(code) {
+        return Some(*guild_id);
+    }
+
+    let guild_id = match serenity::Invite::get(ctx, code, false, false, None).await {
+        Ok(invite) => invite.guild.map(|guild| guild.id),
+        // Unknown invites are treated as foreign, since invites to this server always resolve.
+        // They aren't cached, because the code could become valid later
+        Err(serenity::Error::Http(e)) if e.status_code().map(|s| s.as_u16()) == Some(404) => {
+            return Some(None);
+        },
+        Err(e) => {
+            log::warn!("Couldn't resolve invite {}: {}", code, e);
+            return None;
+        },
+    };
+
+    let mut invite_guilds = data.automod.invite_guilds.lock().unwrap();
+    if invite_guilds.len() >= MAX_CACHED_INVITES {
+        invite_guilds.clear();
+    }
+    invite_guilds.insert(code.to_owned(), guild_id);
+    Some(guild_id)
+}
+
+async fn find_violation(
+    ctx: &serenity::Context,
+    data: &Data,
+    offense: &Offense<'_>,
+) -> Option<LinkViolation> {
+    for captures in URL_REGEX.captures_iter(offense.content) {
+        let host = host_of(&captures[1]);
+        if is_blocked(&data.config.get().automod.blocked_domains, &host) {
+            return Some(LinkViolation::BlockedDomain(host));
+        }
+    }
+
+    for captures in INVITE_REGEX.captures_iter(offense.content) {
+        let Some(invite_guild_id) = invite_guild(ctx, data, &captures[1]).await else {
+            continue;
+        };
+        if invite_guild_id != Some(offense.guild_id) {
+            return Some(LinkViolation::ForeignInvite);
+        }
+    }
+
+    None
+}
+
+/// Deletes messages containing invites to other servers or links to blocked domains and tells
+/// the author why. Returns whether the message was deleted
+pub(super) async fn apply_link_filter(
+    ctx: &serenity::Context,
+    data: &Data,
+    offense: &Offense<'_>,
+) -> Result<bool, Error> {
+    if data.automod.link_filter_disabled_channels.read().unwrap().contains(&offense.channel_id) {
+        return Ok(false);
+    }
+    let Some(violation) = find_violation(ctx, data, offense).await else {
+        return Ok(false);
+    };
+    log::info!("Automod: {} by {}", violation, offense.author_id);
+
+    super::delete_messages(ctx, &[(offense.channel_id, offense.message_id)]).await;
+
+    let notice = format!(
+        "Your message in <#{}> was removed: {}. Please contact a moderator if you think this was \
+         a mistake.",
+        offense.channel_id, violation
+    );
+    let builder = serenity::CreateMessage::new().content(notice);
+    if let Err(e) = offense.author_id.direct_message(ctx, builder).await {
+        log::info!("Couldn't notify {} about removed link: {}", offense.author_id, e);
+    }
+
+    ModLogEntry::new("Automod removed link", data.bot_user_id)
+        .target(offense.author_id)
+        .channel(offense.channel_id)
+        .field("Content", offense.content)
+        .reason(Some(&violation.to_string()))
+        .post_in_guild(ctx, data, offense.guild_id)
+        .await;
+
+    Ok(true)
+}
+
+/// Turns the automod link filter on or off per channel (moderator only)
+///
+/// The link filter removes invites to other servers and links to known phishing domains.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    hide_in_help,
+    category = "Moderation",
+    subcommands("linkfilter_enable", "linkfilter_disable"),
+    subcommand_required
+)]
+pub async fn linkfilter(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Enables the link filter in a channel
+///
+/// ?linkfilter enable [channel]
+#[poise::command(prefix_command, slash_command, guild_only, rename = "enable")]
+pub async fn linkfilter_enable(
+    ctx: Context<'_>,
+    #[description = "Channel to enable the link filter in. Defaults to the current channel"]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    if channel.as_ref().is_some_and(|channel| Some(channel.guild_id) != ctx.guild_id()) {
+        anyhow::bail!("The channel must be in this server");
+    }
+    let channel_id = channel.map_or(ctx.channel_id(), |channel| channel.id);
+
+    sqlx::query("DELETE FROM link_filter_disabled_channels WHERE channel_id = ?")
+        .bind(channel_id.get() as i64)
+        .execute(&ctx.data().database)
+        .await?;
+    ctx.data().automod.link_filter_disabled_channels.write().unwrap().remove(&channel_id);
+
+    ModLogEntry::new("Link filter enabled", ctx.author().id)
+        .channel(channel_id)
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(format!("Link filter enabled in <#{}>", channel_id)).await?;
+
+    Ok(())
+}
+
+/// Disables the link filter in a channel
+///
+/// ?linkfilter disable [channel]
+#[poise::command(prefix_command, slash_command, guild_only, rename = "disable")]
+pub async fn linkfilter_disable(
+    ctx: Context<'_>,
+    #[description = "Channel to disable the link filter in. Defaults to the current channel"]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+    if channel.as_ref().is_some_and(|channel| channel.guild_id != guild_id) {
+        anyhow::bail!("The channel must be in this server");
+    }
+    let channel_id = channel.map_or(ctx.channel_id(), |channel| channel.id);
+
+    sqlx::query(
+        "INSERT INTO link_filter_disabled_channels (channel_id, guild_id) VALUES (?, ?)
+         ON CONFLICT (channel_id) DO NOTHING",
+    )
+    .bind(channel_id.get() as i64)
+    .bind(guild_id.get() as i64)
+    .execute(&ctx.data().database)
+    .await?;
+    ctx.data().automod.link_filter_disabled_channels.write().unwrap().insert(channel_id);
+
+    ModLogEntry::new("Link filter disabled", ctx.author().id)
+        .channel(channel_id)
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(format!("Link filter disabled in <#{}>", channel_id)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_hosts() {
+        assert_eq!(host_of("Example.COM"), "example.com");
+        assert_eq!(host_of("example.com."), "example.com");
+        assert_eq!(host_of("example.com:8080"), "example.com");
+        assert_eq!(host_of("user:password@example.com:8080"), "example.com");
+        assert_eq!(host_of("a@b@example.com"), "example.com");
+        assert_eq!(host_of("[2001:DB8::1]:443"), "2001:db8::1");
+        assert_eq!(host_of("[::1]"), "::1");
+        assert_eq!(host_of("user@[::1]:80"), "::1");
+        assert_eq!(host_of(""), "");
+    }
+
+    #[test]
+    fn finds_hosts_in_urls() {
+        let hosts = URL_REGEX
+            .captures_iter("see https://user@Phish.example:443/login and http://[::1]:80/x")
+            .map(|captures| host_of(&captures[1]))
+            .collect::<Vec<_>>();
+        assert_eq!(hosts, ["phish.example", "::1"]);
+    }
+
+    #[test]
+    fn blocks_domains_and_subdomains() {
+        let blocked_domains = HashSet::from(["phish.example".to_owned(), "::1".to_owned()]);
+        assert!(is_blocked(&blocked_domains, "phish.example"));
+        assert!(is_blocked(&blocked_domains, "login.phish.example"));
+        assert!(is_blocked(&blocked_domains, "a.b.phish.example"));
+        assert!(is_blocked(&blocked_domains, "::1"));
+        assert!(!is_blocked(&blocked_domains, "notphish.example"));
+        assert!(!is_blocked(&blocked_domains, "example"));
+        assert!(!is_blocked(&blocked_domains, "phish.example.com"));
+        assert!(!is_blocked(&blocked_domains, ""));
+        assert!(!is_blocked(&HashSet::new(), "phish.example"));
+    }
+}
@@ -0,0 +1,223 @@
+//! Automatically moderate incoming messages, for example to stop spam floods before moderators
+//! have to clean them up by hand
+
+mod filters;
+mod links;
+mod raid;
+mod spam;
+pub use filters::{filter, load_filters, Filter};
+pub use links::{linkfilter, load_blocked_domains, load_link_filter_disabled_channels};
+pub use raid::{on_member_join, raidmode, RaidConfig, RaidTracker};
+pub use spam::{SpamConfig, SpamTracker};
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Error;
+use poise::serenity_prelude::{self as serenity, EditMember};
+
+use crate::moderation::{record_case, CaseKind, Duration, ModLogEntry};
+use crate::types::Data;
+
+/// The `[automod]` section of the config file
+#[derive(Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutomodConfig {
+    #[serde(rename = "spam")]
+    pub spam_config: SpamConfig,
+    #[serde(rename = "raid")]
+    pub raid_config: RaidConfig,
+    /// How long authors of messages matching a `timeout` filter are timed out for
+    #[serde(deserialize_with = "crate::config::deserialize_chrono_duration")]
+    pub filter_timeout: chrono::Duration,
+    /// File with phishing domains, see [`load_blocked_domains`]
+    pub blocked_domains_file: Option<String>,
+    /// Phishing domains whose links are removed, read from `blocked_domains_file`
+    #[serde(skip)]
+    pub blocked_domains: HashSet<String>,
+}
+
+impl Default for AutomodConfig {
+    fn default() -> Self {
+        Self {
+            spam_config: SpamConfig::default(),
+            raid_config: RaidConfig::default(),
+            filter_timeout: chrono::Duration::minutes(10),
+            blocked_domains_file: None,
+            blocked_domains: HashSet::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Automod {
+    pub spam_tracker: std::sync::Mutex<SpamTracker>,
+    /// Compiled filters of all guilds, reloaded whenever they are changed
+    pub filters: std::sync::RwLock<Vec<Filter>>,
+    /// Channels in which invites and blocked links are allowed
+    pub link_filter_disabled_channels: std::sync::RwLock<HashSet<serenity::ChannelId>>,
+    /// Guilds of the invite codes resolved by the link filter. `None` for invites to group DMs
+    pub invite_guilds: std::sync::Mutex<HashMap<String, Option<serenity::GuildId>>>,
+    pub raid_tracker: std::sync::Mutex<RaidTracker>,
+}
+
+/// The message that triggered an automod rule
+struct Offense<'a> {
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+    message_id: serenity::MessageId,
+    author_id: serenity::UserId,
+    content: &'a str,
+}
+
+/// Moderators and bots are never acted upon
+fn is_exempt(
+    data: &Data,
+    guild_id: serenity::GuildId,
+    author: &serenity::User,
+    member: &serenity::PartialMember,
+) -> bool {
+    author.bot || data.settings(guild_id).is_moderator(&member.roles)
+}
+
+pub async fn on_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<(), Error> {
+    let (Some(guild_id), Some(member)) = (message.guild_id, &message.member) else {
+        return Ok(());
+    };
+    if is_exempt(data, guild_id, &message.author, member) {
+        return Ok(());
+    }
+
+    let offense = Offense {
+        guild_id,
+        channel_id: message.channel_id,
+        message_id: message.id,
+        author_id: message.author.id,
+        content: &message.content,
+    };
+    if filters::apply_filters(ctx, data, &offense).await? {
+        return Ok(());
+    }
+    if links::apply_link_filter(ctx, data, &offense).await? {
+        return Ok(());
+    }
+
+    let spam = data
+        .automod
+        .spam_tracker
+        .lock()
+        .unwrap()
+        .check(&data.config.get().automod.spam_config, message);
+    if let Some((violation, offending_messages)) = spam {
+        log::info!("Automod: {} by {}", violation, message.author.id);
+
+        delete_messages(ctx, &offending_messages).await;
+        timeout_member(
+            ctx,
+            data,
+            &offense,
+            &violation.to_string(),
+            data.config.get().automod.spam_config.timeout,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Edited messages are only checked against the filters and the link filter, since edits can't
+/// flood a channel
+pub async fn on_message_update(
+    ctx: &serenity::Context,
+    data: &Data,
+    event: &serenity::MessageUpdateEvent,
+) -> Result<(), Error> {
+    let (Some(guild_id), Some(author), Some(Some(member)), Some(content)) =
+        (event.guild_id, &event.author, &event.member, &event.content)
+    else {
+        return Ok(());
+    };
+    if is_exempt(data, guild_id, author, member) {
+        return Ok(());
+    }
+
+    let offense = Offense {
+        guild_id,
+        channel_id: event.channel_id,
+        message_id: event.id,
+        author_id: author.id,
+        content,
+    };
+    if filters::apply_filters(ctx, data, &offense).await? {
+        return Ok(());
+    }
+    links::apply_link_filter(ctx, data, &offense).await?;
+
+    Ok(())
+}
+
+/// Deletes the given messages, in bulk where possible. Failures are only logged, since the
+/// messages may have been deleted already
+async fn delete_messages(
+    ctx: &serenity::Context,
+    messages: &[(serenity::ChannelId, serenity::MessageId)],
+) {
+    let mut messages_by_channel = HashMap::<_, Vec<_>>::new();
+    for &(channel_id, message_id) in messages {
+        messages_by_channel.entry(channel_id).or_default().push(message_id);
+    }
+
+    for (channel_id, message_ids) in messages_by_channel {
+        // Bulk deletion requires at least two messages
+        let result = match message_ids.as_slice() {
+            [message_id] => channel_id.delete_message(ctx, message_id).await,
+            message_ids => channel_id.delete_messages(ctx, message_ids).await,
+        };
+        if let Err(e) = result {
+            log::warn!("Automod failed to delete messages in {}: {}", channel_id, e);
+        }
+    }
+}
+
+/// Times out the offender, records it as a case and posts it to the mod log
+async fn timeout_member(
+    ctx: &serenity::Context,
+    data: &Data,
+    offense: &Offense<'_>,
+    reason: &str,
+    duration: chrono::Duration,
+) -> Result<(), Error> {
+    let reason = format!("Automod: {}", reason);
+
+    let until = chrono::Utc::now() + duration;
+    let builder = EditMember::new()
+        .disable_communication_until_datetime(until.into())
+        .audit_log_reason(&reason);
+    offense.guild_id.edit_member(ctx, offense.author_id, builder).await?;
+
+    let case_id = record_case(
+        &data.database,
+        offense.guild_id,
+        CaseKind::Timeout,
+        offense.author_id,
+        data.bot_user_id,
+        Some(&reason),
+        Some(Duration(duration)),
+    )
+    .await?;
+
+    ModLogEntry::new("Automod timeout", data.bot_user_id)
+        .target(offense.author_id)
+        .channel(offense.channel_id)
+        .case(case_id)
+        .field("Duration", Duration(duration).to_string())
+        .field("Content", offense.content)
+        .reason(Some(&reason))
+        .post_in_guild(ctx, data, offense.guild_id)
+        .await;
+
+    Ok(())
+}
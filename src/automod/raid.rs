@@ -0,0 +1,283 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+
+use crate::moderation::{
+    apply_slowmode, lift_slowmodes_of_invocation, ModLogEntry, MAX_SLOWMODE_RATE,
+};
+use crate::types::{Context, Data};
+
+/// Verification level raid mode raises the guild to: members must have been in the guild for ten
+/// minutes before they can send messages
+const RAID_VERIFICATION_LEVEL: serenity::VerificationLevel = serenity::VerificationLevel::High;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RaidConfig {
+    /// More than this many members joining within `join_window` is a raid
+    pub max_joins: usize,
+    #[serde(deserialize_with = "crate::config::deserialize_std_duration")]
+    pub join_window: Duration,
+    /// Slowmode applied to public channels during raid mode, in seconds
+    pub slowmode_rate: u16,
+    /// How long the raid mode slowmodes last if raid mode isn't ended by hand
+    #[serde(deserialize_with = "crate::config::deserialize_chrono_duration")]
+    pub slowmode_duration: chrono::Duration,
+}
+
+impl Default for RaidConfig {
+    fn default() -> Self {
+        Self {
+            max_joins: 10,
+            join_window: Duration::from_secs(30),
+            slowmode_rate: 30,
+            slowmode_duration: chrono::Duration::hours(6),
+        }
+    }
+}
+
+impl RaidConfig {
+    /// Rejects values Discord doesn't accept, so that raid mode doesn't fail in the middle of a
+    /// raid
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.slowmode_rate > MAX_SLOWMODE_RATE {
+            anyhow::bail!(
+                "automod.raid.slowmode_rate must be at most {} seconds",
+                MAX_SLOWMODE_RATE
+            );
+        }
+        if self.slowmode_duration < chrono::Duration::minutes(1)
+            || u32::try_from(self.slowmode_duration.num_minutes()).is_err()
+        {
+            anyhow::bail!("automod.raid.slowmode_duration must be between 1m and {}m", u32::MAX);
+        }
+        Ok(())
+    }
+}
+
+/// Keeps track of recent joins of every guild to detect join spikes
+#[derive(Debug, Default)]
+pub struct RaidTracker {
+    recent_joins: HashMap<serenity::GuildId, VecDeque<Instant>>,
+}
+
+impl RaidTracker {
+    /// Registers a join and returns whether the guild is being raided. The join history is
+    /// forgotten on detection, so that a single raid only triggers once
+    pub fn check(&mut self, config: &RaidConfig, guild_id: serenity::GuildId) -> bool {
+        let now = Instant::now();
+
+        let joins = self.recent_joins.entry(guild_id).or_default();
+        while joins.front().is_some_and(|&joined| now.duration_since(joined) >= config.join_window)
+        {
+            joins.pop_front();
+        }
+        joins.push_back(now);
+
+        if joins.len() > config.max_joins {
+            self.recent_joins.remove(&guild_id);
+            return true;
+        }
+        false
+    }
+}
+
+pub async fn on_member_join(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    let config = data.config.get();
+    let is_raid = data
+        .automod
+        .raid_tracker
+        .lock()
+        .unwrap()
+        .check(&config.automod.raid_config, member.guild_id);
+    if !is_raid {
+        return Ok(());
+    }
+
+    log::warn!("Join spike detected in {}, entering raid mode", member.guild_id);
+    start_raid_mode(ctx, data, member.guild_id, data.bot_user_id).await?;
+
+    Ok(())
+}
+
+/// Raises the verification level, slows down all public text channels and alerts the moderators.
+/// Returns false if the guild is already in raid mode
+async fn start_raid_mode(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    actor: serenity::UserId,
+) -> Result<bool, Error> {
+    let started_at = chrono::Utc::now().timestamp_millis();
+    let previous_verification_level = guild_id.to_partial_guild(ctx).await?.verification_level;
+
+    let inserted = sqlx::query(
+        "INSERT INTO raid_modes (guild_id, previous_verification_level, started_at)
+         VALUES (?, ?, ?)
+         ON CONFLICT (guild_id) DO NOTHING",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(u8::from(previous_verification_level))
+    .bind(started_at)
+    .execute(&data.database)
+    .await?
+    .rows_affected();
+    if inserted == 0 {
+        return Ok(false);
+    }
+
+    if previous_verification_level < RAID_VERIFICATION_LEVEL {
+        let builder = serenity::EditGuild::new()
+            .verification_level(RAID_VERIFICATION_LEVEL)
+            .audit_log_reason("Raid mode");
+        if let Err(e) = guild_id.edit(ctx, builder).await {
+            // Otherwise raid mode would look active, and later join spikes would be ignored
+            sqlx::query("DELETE FROM raid_modes WHERE guild_id = ? AND started_at = ?")
+                .bind(guild_id.get() as i64)
+                .bind(started_at)
+                .execute(&data.database)
+                .await?;
+            return Err(e.into());
+        }
+    }
+
+    let config = data.config.get();
+    let raid_config = &config.automod.raid_config;
+    let settings = data.settings(guild_id);
+    let mut slowed_down_channels = 0;
+    for channel in guild_id.channels(ctx).await?.values() {
+        if channel.kind != serenity::ChannelType::Text
+            || crate::helpers::is_private_channel(channel)
+            || channel.rate_limit_per_user.unwrap_or(0) >= raid_config.slowmode_rate
+        {
+            continue;
+        }
+
+        // Registered with the raid mode start as invocation time, so that ending raid mode
+        // restores exactly these channels
+        let result = apply_slowmode(
+            ctx,
+            &data.database,
+            channel,
+            // Checked by RaidConfig::validate
+            raid_config.slowmode_duration.num_minutes() as u32,
+            raid_config.slowmode_rate,
+            started_at,
+        )
+        .await;
+        match result {
+            Ok(()) => slowed_down_channels += 1,
+            Err(e) => log::warn!("Failed to apply raid mode slowmode to {}: {}", channel.id, e),
+        }
+    }
+
+    ModLogEntry::new("Raid mode started", actor)
+        .field("Slowed down channels", slowed_down_channels.to_string())
+        .field("Slowmode rate", format!("{} seconds", raid_config.slowmode_rate))
+        .post_in_guild(ctx, data, guild_id)
+        .await;
+
+    if let Some(reports_channel) = settings.reports_channel {
+        let content = format!(
+            "{} Raid mode has been enabled: verification level raised and {} channels slowed \
+             down. Use `?raidmode end` once the raid is over.",
+            settings.mod_role_mention(),
+            slowed_down_channels
+        );
+        let builder = serenity::CreateMessage::new()
+            .content(content)
+            .allowed_mentions(serenity::CreateAllowedMentions::new().roles(settings.mod_role_id));
+        reports_channel.send_message(ctx, builder).await?;
+    }
+
+    Ok(true)
+}
+
+/// Restores the verification level and the slowmode of every channel slowed down by raid mode.
+/// Returns false if the guild isn't in raid mode
+async fn end_raid_mode(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    actor: serenity::UserId,
+) -> Result<bool, Error> {
+    let raid_mode: Option<(u8, i64)> = sqlx::query_as(
+        "DELETE FROM raid_modes WHERE guild_id = ?
+         RETURNING previous_verification_level, started_at",
+    )
+    .bind(guild_id.get() as i64)
+    .fetch_optional(&data.database)
+    .await?;
+    let Some((previous_verification_level, started_at)) = raid_mode else {
+        return Ok(false);
+    };
+
+    let builder = serenity::EditGuild::new()
+        .verification_level(previous_verification_level)
+        .audit_log_reason("Raid mode ended");
+    guild_id.edit(ctx, builder).await?;
+
+    let restored_channels = lift_slowmodes_of_invocation(ctx, &data.database, started_at).await?;
+
+    ModLogEntry::new("Raid mode ended", actor)
+        .field("Restored channels", restored_channels.len().to_string())
+        .post_in_guild(ctx, data, guild_id)
+        .await;
+
+    Ok(true)
+}
+
+/// Manages raid mode (moderator only)
+///
+/// Raid mode starts automatically when many members join within a short time. It raises the \
+/// verification level and slows down all public channels until it's ended.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    hide_in_help,
+    category = "Moderation",
+    subcommands("raidmode_start", "raidmode_end"),
+    subcommand_required
+)]
+pub async fn raidmode(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Starts raid mode by hand
+#[poise::command(prefix_command, slash_command, guild_only, rename = "start")]
+pub async fn raidmode_start(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+    ctx.defer().await?;
+
+    if start_raid_mode(ctx.serenity_context(), ctx.data(), guild_id, ctx.author().id).await? {
+        ctx.say("Raid mode started").await?;
+    } else {
+        ctx.say("Raid mode is already active").await?;
+    }
+
+    Ok(())
+}
+
+/// Ends raid mode, restoring the verification level and every channel's slowmode
+#[poise::command(prefix_command, slash_command, guild_only, rename = "end")]
+pub async fn raidmode_end(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+    ctx.defer().await?;
+
+    if end_raid_mode(ctx.serenity_context(), ctx.data(), guild_id, ctx.author().id).await? {
+        ctx.say("Raid mode ended, channel settings have been restored").await?;
+    } else {
+        ctx.say("Raid mode isn't active").await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use poise::serenity_prelude as serenity;
+
+/// Above this many tracked users, users without recent messages are dropped from the tracker
+const TRACKER_CLEANUP_THRESHOLD: usize = 1000;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpamConfig {
+    /// A user sending more than this many messages within `message_window` is flooding
+    pub max_messages: usize,
+    #[serde(deserialize_with = "crate::config::deserialize_std_duration")]
+    pub message_window: Duration,
+    /// A user sending the same message more than this many times within `duplicate_window`,
+    /// possibly in different channels, is spamming
+    pub max_duplicates: usize,
+    #[serde(deserialize_with = "crate::config::deserialize_std_duration")]
+    pub duplicate_window: Duration,
+    /// A single message mentioning more than this many users and roles is a mass mention
+    pub max_mentions: usize,
+    /// How long offenders are timed out for
+    #[serde(deserialize_with = "crate::config::deserialize_chrono_duration")]
+    pub timeout: chrono::Duration,
+}
+
+impl Default for SpamConfig {
+    fn default() -> Self {
+        Self {
+            max_messages: 7,
+            message_window: Duration::from_secs(5),
+            max_duplicates: 3,
+            duplicate_window: Duration::from_secs(60),
+            max_mentions: 10,
+            timeout: chrono::Duration::hours(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpamViolation {
+    MessageFlood,
+    DuplicateMessages,
+    MassMention,
+}
+
+impl std::fmt::Display for SpamViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::MessageFlood => "Sending messages too fast",
+            Self::DuplicateMessages => "Repeatedly sending the same message",
+            Self::MassMention => "Mentioning too many users",
+        })
+    }
+}
+
+#[derive(Debug)]
+struct RecentMessage {
+    channel_id: serenity::ChannelId,
+    message_id: serenity::MessageId,
+    /// Normalized for duplicate detection
+    content: String,
+    received: Instant,
+}
+
+/// Keeps track of the recent messages of every user to detect floods and duplicates
+#[derive(Debug, Default)]
+pub struct SpamTracker {
+    recent_messages: HashMap<serenity::UserId, VecDeque<RecentMessage>>,
+}
+
+impl SpamTracker {
+    /// Registers a new message and checks it against the spam rules. On violation, returns the
+    /// offending messages to delete and forgets the user's history, so that the same messages
+    /// don't trigger twice
+    pub fn check(
+        &mut self,
+        config: &SpamConfig,
+        message: &serenity::Message,
+    ) -> Option<(SpamViolation, Vec<(serenity::ChannelId, serenity::MessageId)>)> {
+        let now = Instant::now();
+        let tracked_window = config.message_window.max(config.duplicate_window);
+
+        if self.recent_messages.len() > TRACKER_CLEANUP_THRESHOLD {
+            self.recent_messages.retain(|_, messages| {
+                messages.back().is_some_and(|m| now.duration_since(m.received) < tracked_window)
+            });
+        }
+
+        let messages = self.recent_messages.entry(message.author.id).or_default();
+        while messages.front().is_some_and(|m| now.duration_since(m.received) >= tracked_window) {
+            messages.pop_front();
+        }
+
+        let content = message.content.trim().to_lowercase();
+        messages.push_back(RecentMessage {
+            channel_id: message.channel_id,
+            message_id: message.id,
+            content: content.clone(),
+            received: now,
+        });
+
+        let mention_count = message.mentions.len()
+            + message.mention_roles.len()
+            + usize::from(message.mention_everyone);
+
+        let recent = |window: Duration| {
+            messages.iter().filter(move |m| now.duration_since(m.received) < window)
+        };
+        let duplicates = || {
+            recent(config.duplicate_window).filter(|m| !content.is_empty() && m.content == content)
+        };
+
+        let (violation, offending_messages) = if mention_count > config.max_mentions {
+            (SpamViolation::MassMention, vec![(message.channel_id, message.id)])
+        } else if duplicates().count() > config.max_duplicates {
+            let offending = duplicates().map(|m| (m.channel_id, m.message_id)).collect();
+            (SpamViolation::DuplicateMessages, offending)
+        } else if recent(config.message_window).count() > config.max_messages {
+            let offending =
+                recent(config.message_window).map(|m| (m.channel_id, m.message_id)).collect();
+            (SpamViolation::MessageFlood, offending)
+        } else {
+            return None;
+        };
+
+        self.recent_messages.remove(&message.author.id);
+        Some((violation, offending_messages))
+    }
+}
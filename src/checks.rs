@@ -1,12 +1,19 @@
 use anyhow::Error;
+use poise::serenity_prelude as serenity;
 
 use crate::types::Context;
 
+/// Like [`check_is_moderator`], but without responding or failing if the author is no member
+pub async fn is_moderator(ctx: Context<'_>) -> bool {
+    let settings = ctx.data().settings(ctx.guild_id());
+    ctx.author_member().await.is_some_and(|author| settings.is_moderator(&author.roles))
+}
+
 pub async fn check_is_moderator(ctx: Context<'_>) -> Result<bool, Error> {
     let author =
         ctx.author_member().await.ok_or(anyhow::anyhow!("Failed to fetch server member."))?;
 
-    let user_has_moderator_role = author.roles.contains(&ctx.data().mod_role_id);
+    let user_has_moderator_role = ctx.data().settings(ctx.guild_id()).is_moderator(&author.roles);
 
     if !user_has_moderator_role {
         ctx.send(
@@ -19,3 +26,141 @@ pub async fn check_is_moderator(ctx: Context<'_>) -> Result<bool, Error> {
 
     Ok(user_has_moderator_role)
 }
+
+/// Rejects commands of the categories disabled with `?settings disable`
+pub async fn check_category_enabled(ctx: Context<'_>) -> Result<bool, Error> {
+    let Some(category) = &ctx.command().category else {
+        return Ok(true);
+    };
+    if !ctx.data().settings(ctx.guild_id()).is_category_disabled(category) {
+        return Ok(true);
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("This command is disabled in this server.")
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(false)
+}
+
+/// A rule configured with `?permissions`, allowing a role or members with certain permissions to
+/// use a command or category
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PermissionRule {
+    pub id: i64,
+    pub target: String,
+    pub role_id: Option<i64>,
+    pub permissions: Option<i64>,
+}
+
+/// Rule targets that apply to the command, from most to least specific: the command itself, its
+/// parent commands, and its category
+fn rule_targets(command: &poise::Command<crate::types::Data, Error>) -> Vec<String> {
+    let mut targets = Vec::new();
+    let mut qualified_name = command.qualified_name.to_lowercase();
+    loop {
+        targets.push(qualified_name.clone());
+        match qualified_name.rsplit_once(' ') {
+            Some((parent, _)) => qualified_name = parent.to_owned(),
+            None => break,
+        }
+    }
+    if let Some(category) = &command.category {
+        targets.push(format!("category:{}", category.to_lowercase()));
+    }
+    targets
+}
+
+/// Commands that only moderators may ever use, since they control the bot or who may use which
+/// commands. Rules can't be configured for them
+pub const ALWAYS_MODERATOR_ONLY: &[&str] = &["permissions", "raidmode", "register"];
+
+/// Commands that only moderators may use until a rule configured with `?permissions` for the
+/// command itself says otherwise. Entries are rule targets, so subcommands of these commands are
+/// included
+const MODERATOR_ONLY_BY_DEFAULT: &[&str] = &[
+    "areply",
+    "cases",
+    "cleanup",
+    "close",
+    "filter",
+    "linkfilter",
+    "lockdown",
+    "move",
+    "playground_health",
+    "purge",
+    "reply",
+    "slowmode",
+    "tempban",
+    "timeout",
+    "unlock",
+    "untimeout",
+    "warn",
+];
+
+/// Runs before every command. If permission rules are configured for the command, the most
+/// specific target with rules decides, and the author needs one of its roles or permissions.
+/// Without rules, the commands in [`MODERATOR_ONLY_BY_DEFAULT`] are restricted to moderators and
+/// all others are open to everyone. Rules for a category don't apply to those commands, and no
+/// rules apply to [`ALWAYS_MODERATOR_ONLY`]. Moderators may always use every command
+pub async fn check_command_permissions(ctx: Context<'_>) -> Result<bool, Error> {
+    let targets = rule_targets(ctx.command());
+    let is_listed = |list: &[&str]| targets.iter().any(|target| list.contains(&target.as_str()));
+    let always_moderator_only = is_listed(ALWAYS_MODERATOR_ONLY);
+    let moderator_only_by_default = always_moderator_only || is_listed(MODERATOR_ONLY_BY_DEFAULT);
+
+    let Some(guild_id) = ctx.guild_id() else {
+        if moderator_only_by_default {
+            ctx.say("This command only works inside guilds").await?;
+        }
+        return Ok(!moderator_only_by_default);
+    };
+    if always_moderator_only {
+        return check_is_moderator(ctx).await;
+    }
+
+    let rules = targets
+        .iter()
+        .filter(|target| !(moderator_only_by_default && target.starts_with("category:")))
+        .map(|target| ctx.data().guild_settings.permission_rules(guild_id, target))
+        .find(|rules| !rules.is_empty());
+    let Some(rules) = rules else {
+        return if moderator_only_by_default { check_is_moderator(ctx).await } else { Ok(true) };
+    };
+
+    let author =
+        ctx.author_member().await.ok_or(anyhow::anyhow!("Failed to fetch server member."))?;
+    if ctx.data().settings(guild_id).is_moderator(&author.roles) {
+        return Ok(true);
+    }
+
+    let mut allowed = rules.iter().any(|rule| {
+        rule.role_id
+            .is_some_and(|role_id| author.roles.contains(&serenity::RoleId::new(role_id as u64)))
+    });
+    if !allowed && rules.iter().any(|rule| rule.permissions.is_some()) {
+        let guild = ctx.partial_guild().await.ok_or(anyhow::anyhow!("Failed to fetch server."))?;
+        let author_permissions = match ctx.guild_channel().await {
+            Some(channel) => guild.user_permissions_in(&channel, &author),
+            None => guild.member_permissions(&author),
+        };
+        allowed = author_permissions.administrator()
+            || rules.iter().filter_map(|rule| rule.permissions).any(|permissions| {
+                author_permissions
+                    .contains(serenity::Permissions::from_bits_truncate(permissions as u64))
+            });
+    }
+
+    if !allowed {
+        ctx.send(
+            poise::CreateReply::default()
+                .content("You don't have permission to use this command.")
+                .ephemeral(true),
+        )
+        .await?;
+    }
+
+    Ok(allowed)
+}
@@ -0,0 +1,199 @@
+//! Settings loaded from a TOML file, which can be reloaded at runtime with `?reload_config`.
+//! Secrets and settings needed to connect at all, like the bot token and the database URL, stay
+//! environment variables
+
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Deserializer};
+
+use crate::types::{Context, Data};
+use crate::{automod, cooldowns, godbolt, moderation, playground};
+
+fn default_prefixes() -> Vec<String> {
+    [
+        "?",
+        "cachyos_bot ",
+        "🦀 ",
+        "🦀",
+        "<:ferris:358652670585733120> ",
+        "<:ferris:358652670585733120>",
+        "<:ferrisballSweat:678714352450142239> ",
+        "<:ferrisballSweat:678714352450142239>",
+        "<:ferrisCat:1183779700485664820> ",
+        "<:ferrisCat:1183779700485664820>",
+        "<:ferrisOwO:579331467000283136> ",
+        "<:ferrisOwO:579331467000283136>",
+    ]
+    .map(String::from)
+    .into()
+}
+
+fn default_prefix_patterns() -> Vec<String> {
+    vec!["(yo |hey )?(crab|ferris|fewwis),? can you (please |pwease )?".into()]
+}
+
+fn default_new_account_threshold() -> moderation::Duration {
+    moderation::Duration::from_secs(60 * 60 * 24 * 7)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Literal command prefixes, tried in order, so longer prefixes must come before their own
+    /// prefixes
+    #[serde(default = "default_prefixes")]
+    pub prefixes: Vec<String>,
+    /// Regex command prefixes, matched at the start of the message
+    #[serde(default = "default_prefix_patterns")]
+    pub prefix_patterns: Vec<String>,
+    #[serde(skip)]
+    compiled_prefix_patterns: Vec<regex::Regex>,
+
+    /// Settings of the guild configured as DISCORD_SERVER_ID, used where `?settings` doesn't
+    /// override them. Other guilds only use the settings from `?settings`
+    #[serde(default)]
+    pub mod_role_id: Option<serenity::RoleId>,
+    #[serde(default)]
+    pub reports_channel: Option<serenity::ChannelId>,
+    #[serde(default)]
+    pub mod_log_channel: Option<serenity::ChannelId>,
+    #[serde(default)]
+    pub message_log_channel: Option<serenity::ChannelId>,
+    #[serde(default)]
+    pub member_log_channel: Option<serenity::ChannelId>,
+    /// Staff channel in which modmail threads are opened. Modmail is disabled without it
+    #[serde(default)]
+    pub modmail_channel: Option<serenity::ChannelId>,
+    /// Accounts younger than this are highlighted in the member log
+    #[serde(default = "default_new_account_threshold", deserialize_with = "deserialize_from_str")]
+    pub new_account_threshold: moderation::Duration,
+
+    #[serde(default)]
+    pub playground: playground::PlaygroundDefaults,
+    #[serde(default)]
+    pub godbolt: godbolt::GodboltDefaults,
+    #[serde(default)]
+    pub automod: automod::AutomodConfig,
+    #[serde(default)]
+    pub cooldowns: cooldowns::CooldownConfig,
+}
+
+impl Config {
+    pub fn load(path: &std::path::Path) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Couldn't read config file {}: {}", path.display(), e))?;
+        let mut config: Config = toml::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Invalid config file {}: {}", path.display(), e))?;
+
+        config.compiled_prefix_patterns = config
+            .prefix_patterns
+            .iter()
+            .map(|pattern| regex::Regex::new(pattern))
+            .collect::<Result<_, _>>()?;
+        config.automod.raid_config.validate()?;
+        if let Some(path) = &config.automod.blocked_domains_file {
+            config.automod.blocked_domains = automod::load_blocked_domains(path)?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Deserializes a string with the type's [`FromStr`](std::str::FromStr) implementation, for
+/// example durations like `30s`
+pub fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+}
+
+pub fn deserialize_std_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<std::time::Duration, D::Error> {
+    let duration: moderation::Duration = deserialize_from_str(deserializer)?;
+    duration.0.to_std().map_err(serde::de::Error::custom)
+}
+
+pub fn deserialize_chrono_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<chrono::Duration, D::Error> {
+    let duration: moderation::Duration = deserialize_from_str(deserializer)?;
+    Ok(duration.0)
+}
+
+/// The current configuration, shared between the framework and background tasks like the
+/// moderation scheduler
+#[derive(Debug, Clone)]
+pub struct SharedConfig {
+    path: PathBuf,
+    current: Arc<RwLock<Arc<Config>>>,
+}
+
+impl SharedConfig {
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let config = Config::load(&path)?;
+        Ok(Self { path, current: Arc::new(RwLock::new(Arc::new(config))) })
+    }
+
+    /// Returns a snapshot of the configuration. Commands should keep using the same snapshot, so
+    /// that a concurrent reload doesn't mix old and new settings
+    pub fn get(&self) -> Arc<Config> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Reads the config file again. The previous configuration stays active if it's invalid
+    pub fn reload(&self) -> Result<(), Error> {
+        let config = Config::load(&self.path)?;
+        *self.current.write().unwrap() = Arc::new(config);
+        Ok(())
+    }
+}
+
+/// Strips the configured command prefixes off messages
+pub async fn strip_prefix<'a>(
+    message: &'a serenity::Message,
+    data: &'a Data,
+) -> Result<Option<(&'a str, &'a str)>, Error> {
+    let config = data.config.get();
+    let content = message.content.as_str();
+
+    if let Some(prefix) = config.prefixes.iter().find(|prefix| content.starts_with(prefix.as_str()))
+    {
+        return Ok(Some(content.split_at(prefix.len())));
+    }
+    for pattern in &config.compiled_prefix_patterns {
+        if let Some(prefix) = pattern.find(content).filter(|prefix| prefix.start() == 0) {
+            return Ok(Some(content.split_at(prefix.end())));
+        }
+    }
+    Ok(None)
+}
+
+/// Reloads the config file without restarting the bot (owner only)
+///
+/// Registered commands don't change on reload, so `/report` and the modmail commands reply with \
+/// an error while their channels aren't configured.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    owners_only,
+    hide_in_help,
+    category = "Miscellaneous"
+)]
+pub async fn reload_config(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.data().config.reload()?;
+
+    moderation::ModLogEntry::from_context(ctx, "Configuration reloaded")
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say("Reloaded the configuration").await?;
+
+    Ok(())
+}
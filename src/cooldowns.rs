@@ -0,0 +1,158 @@
+//! Rate limit commands which call external services, like the playground and godbolt commands
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+
+use crate::types::{Context, Data};
+
+/// Invocations that never finished, for example because the command panicked or had its own
+/// error handler, stop counting towards the concurrency cap after this long
+const MAX_EXECUTION_TIME: Duration = Duration::from_secs(2 * 60);
+
+/// The `[cooldowns]` section of the config file
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CooldownConfig {
+    /// Commands of these categories are rate limited
+    pub categories: Vec<String>,
+    /// How long a user has to wait between invocations
+    #[serde(deserialize_with = "crate::config::deserialize_std_duration")]
+    pub per_user: Duration,
+    /// How long anyone has to wait between invocations in the same channel
+    #[serde(deserialize_with = "crate::config::deserialize_std_duration")]
+    pub per_channel: Duration,
+    /// How many rate limited commands may run at the same time
+    pub max_concurrent: usize,
+}
+
+impl Default for CooldownConfig {
+    fn default() -> Self {
+        Self {
+            categories: vec!["Playground".into(), "Godbolt".into()],
+            per_user: Duration::from_secs(5),
+            per_channel: Duration::from_secs(2),
+            max_concurrent: 4,
+        }
+    }
+}
+
+impl CooldownConfig {
+    fn applies_to(&self, command: &poise::Command<Data, Error>) -> bool {
+        command.category.as_ref().is_some_and(|category| self.categories.contains(category))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Throttled {
+    User(Duration),
+    Channel(Duration),
+    Busy,
+}
+
+impl std::fmt::Display for Throttled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::User(remaining) => write!(
+                f,
+                "Slow down a little! You can use this command again in {} seconds.",
+                remaining.as_secs() + 1
+            ),
+            Self::Channel(remaining) => write!(
+                f,
+                "This command was just used in this channel, try again in {} seconds.",
+                remaining.as_secs() + 1
+            ),
+            Self::Busy => f.write_str(
+                "Too many commands are running right now, please try again in a few seconds.",
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CooldownState {
+    last_user_invocation: HashMap<serenity::UserId, Instant>,
+    last_channel_invocation: HashMap<serenity::ChannelId, Instant>,
+    /// Start times of running invocations, by invocation ID
+    running: HashMap<u64, Instant>,
+}
+
+#[derive(Debug, Default)]
+pub struct Cooldowns {
+    state: std::sync::Mutex<CooldownState>,
+}
+
+impl Cooldowns {
+    /// Registers the invocation as running, unless it's throttled
+    fn try_start(
+        &self,
+        config: &CooldownConfig,
+        invocation_id: u64,
+        user_id: serenity::UserId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Throttled> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        let remaining = |last_invocation: Option<&Instant>, cooldown: Duration| {
+            last_invocation
+                .map(|&last_invocation| cooldown.saturating_sub(now - last_invocation))
+                .filter(|remaining| !remaining.is_zero())
+        };
+        if let Some(remaining) =
+            remaining(state.last_user_invocation.get(&user_id), config.per_user)
+        {
+            return Err(Throttled::User(remaining));
+        }
+        if let Some(remaining) =
+            remaining(state.last_channel_invocation.get(&channel_id), config.per_channel)
+        {
+            return Err(Throttled::Channel(remaining));
+        }
+
+        state.running.retain(|_, &mut started| now - started < MAX_EXECUTION_TIME);
+        if state.running.len() >= config.max_concurrent {
+            return Err(Throttled::Busy);
+        }
+
+        // Expired entries are cleaned up whenever a new invocation is registered
+        let longest_cooldown = config.per_user.max(config.per_channel);
+        state.last_user_invocation.retain(|_, &mut last| now - last < longest_cooldown);
+        state.last_channel_invocation.retain(|_, &mut last| now - last < longest_cooldown);
+
+        state.last_user_invocation.insert(user_id, now);
+        state.last_channel_invocation.insert(channel_id, now);
+        state.running.insert(invocation_id, now);
+        Ok(())
+    }
+
+    /// Frees the invocation's slot of the concurrency cap
+    pub fn finish(&self, invocation_id: u64) {
+        self.state.lock().unwrap().running.remove(&invocation_id);
+    }
+}
+
+/// Runs before every command and throttles the rate limited categories. Moderators are exempt
+pub async fn check_cooldowns(ctx: Context<'_>) -> Result<bool, Error> {
+    let config = ctx.data().config.get();
+    if !config.cooldowns.applies_to(ctx.command()) || crate::checks::is_moderator(ctx).await {
+        return Ok(true);
+    }
+
+    match ctx.data().cooldowns.try_start(
+        &config.cooldowns,
+        ctx.id(),
+        ctx.author().id,
+        ctx.channel_id(),
+    ) {
+        Ok(()) => Ok(true),
+        Err(throttled) => {
+            ctx.send(poise::CreateReply::default().content(throttled.to_string()).ephemeral(true))
+                .await?;
+            Ok(false)
+        },
+    }
+}
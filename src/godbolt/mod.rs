@@ -1,6 +1,6 @@
 mod targets;
 use targets::compiler_id_and_flags;
-pub use targets::{targets_cpp, targets_rust, GodboltMetadata};
+pub use targets::{targets_cpp, targets_rust, GodboltDefaults, GodboltMetadata};
 
 use crate::{Context, Error};
 
@@ -406,7 +406,8 @@ pub async fn play_cpp(
 /// ```
 /// Optional arguments:
 /// - `language`: language to use. Defaults to `rust`. Possible values: `rust`, `c++`
-/// - `flags`: flags to pass to compiler invocation. Defaults to `"-Copt-level=3 --edition=2021"`
+/// - `flags`: flags to pass to compiler invocation. Defaults to `"-Copt-level=3"`. `--edition` is
+///   added unless given, with the default edition of the playground commands
 /// - `compiler`: compiler version to invoke. Defaults to `nightly`. Possible values: `nightly`,
 ///   `beta` or full version like `1.45.2`
 #[poise::command(prefix_command, broadcast_typing, track_edits, category = "Godbolt")]
@@ -435,7 +436,8 @@ fn strip_llvm_mca_result(text: &str) -> &str {
 /// ```
 /// Optional arguments:
 /// - `language`: language to use. Defaults to `rust`. Possible values: `rust`, `c++`
-/// - `flags`: flags to pass to compiler invocation. Defaults to `"-Copt-level=3 --edition=2021"`
+/// - `flags`: flags to pass to compiler invocation. Defaults to `"-Copt-level=3"`. `--edition` is
+///   added unless given, with the default edition of the playground commands
 /// - `compiler`: compiler version to invoke. Defaults to `nightly`. Possible values: `nightly`,
 ///   `beta` or full version like `1.45.2`
 #[poise::command(prefix_command, broadcast_typing, track_edits, category = "Godbolt")]
@@ -462,7 +464,8 @@ pub async fn mca(
 /// ```
 /// Optional arguments:
 /// - `language`: language to use. Defaults to `rust`. Possible values: `rust`, `c++`
-/// - `flags`: flags to pass to compiler invocation. Defaults to `"-Copt-level=3 --edition=2021"`
+/// - `flags`: flags to pass to compiler invocation. Defaults to `"-Copt-level=3"`. `--edition` is
+///   added unless given, with the default edition of the playground commands
 /// - `compiler`: compiler version to invoke. Defaults to `nightly`. Possible values: `nightly`,
 ///   `beta` or full version like `1.45.2`
 #[poise::command(prefix_command, broadcast_typing, track_edits, category = "Godbolt")]
@@ -492,7 +495,8 @@ pub async fn llvmir(
 /// ```
 /// Optional arguments:
 /// - `language`: language to use. Defaults to `rust`. Possible values: `rust`, `c++`
-/// - `flags`: flags to pass to compiler invocation. Defaults to `"-Copt-level=3 --edition=2021"`
+/// - `flags`: flags to pass to compiler invocation. Defaults to `"-Copt-level=3"`. `--edition` is
+///   added unless given, with the default edition of the playground commands
 /// - `compiler`: compiler version to invoke. Defaults to `nightly`. Possible values: `nightly`,
 ///   `beta` or full version like `1.45.2`
 #[poise::command(prefix_command, broadcast_typing, track_edits, hide_in_help, category = "Godbolt")]
@@ -158,6 +158,28 @@ async fn fetch_godbolt_cpp_metadata(
     data.godbolt_cpp_targets.lock().unwrap()
 }
 
+/// The `[godbolt]` section of the config file: compilers and flags used when a command doesn't
+/// specify them
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GodboltDefaults {
+    pub rustc: String,
+    pub rust_flags: String,
+    pub cpp_compiler: String,
+    pub cpp_flags: String,
+}
+
+impl Default for GodboltDefaults {
+    fn default() -> Self {
+        Self {
+            rustc: "nightly".into(),
+            rust_flags: "-Copt-level=3".into(),
+            cpp_compiler: "clang_trunk".into(),
+            cpp_flags: "-std=c++20 -O3".into(),
+        }
+    }
+}
+
 // Generates godbolt-compatible compiler identifier and flags from command input
 pub(super) async fn compiler_id_and_flags(
     data: &Data,
@@ -181,7 +203,9 @@ async fn rustc_id_and_flags(
     params: &poise::KeyValueArgs,
     mode: GodboltMode,
 ) -> Result<(String, String), Error> {
-    let rustc = params.get("rustc").unwrap_or("nightly");
+    let config = data.config.get();
+    let defaults = &config.godbolt;
+    let rustc = params.get("rustc").unwrap_or(&defaults.rustc);
     let targets = fetch_godbolt_rust_metadata(data).await.targets.clone();
     let target =
         targets.into_iter().find(|target| target.semver == rustc.trim()).ok_or(anyhow::anyhow!(
@@ -189,7 +213,11 @@ async fn rustc_id_and_flags(
              `1.45.2`. Run ?targets_rust for a full list",
         ))?;
 
-    let mut flags = params.get("flags").unwrap_or("-Copt-level=3 --edition=2021").to_owned();
+    let mut flags = params.get("flags").unwrap_or(&defaults.rust_flags).to_owned();
+    // The default edition is configured once for the playground and godbolt commands
+    if !flags.contains("--edition") {
+        flags += &format!(" --edition={}", config.playground.edition.as_str());
+    }
     if mode == GodboltMode::LlvmIr {
         flags += " --emit=llvm-ir -Cdebuginfo=0";
     }
@@ -206,7 +234,8 @@ async fn cpp_id_and_flags(
     params: &poise::KeyValueArgs,
     _mode: GodboltMode,
 ) -> Result<(String, String), Error> {
-    let cppcompiler = params.get("compiler").unwrap_or("clang_trunk");
+    let defaults = data.config.get().godbolt.clone();
+    let cppcompiler = params.get("compiler").unwrap_or(&defaults.cpp_compiler);
     let targets = fetch_godbolt_cpp_metadata(data).await.targets.clone();
     let target = targets.into_iter().find(|target| target.id == cppcompiler.trim()).ok_or(
         anyhow::anyhow!(
@@ -215,7 +244,7 @@ async fn cpp_id_and_flags(
         ),
     )?;
 
-    let flags = params.get("flags").unwrap_or("-std=c++20 -O3").to_owned();
+    let flags = params.get("flags").unwrap_or(&defaults.cpp_flags).to_owned();
 
     Ok((target.id, flags))
 }
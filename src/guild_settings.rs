@@ -0,0 +1,451 @@
+//! Settings that differ between the guilds the bot runs in, like the moderator role and the log
+//! channels. They are stored in the database and managed with `?settings`
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use anyhow::Error;
+use poise::{serenity_prelude as serenity, ChoiceParameter as _};
+
+use crate::checks::PermissionRule;
+use crate::config::Config;
+use crate::moderation::ModLogEntry;
+use crate::types::Context;
+
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+struct StoredGuildSettings {
+    guild_id: i64,
+    mod_role_id: Option<i64>,
+    reports_channel: Option<i64>,
+    mod_log_channel: Option<i64>,
+    message_log_channel: Option<i64>,
+    member_log_channel: Option<i64>,
+    disabled_categories: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct StoredPermissionRule {
+    guild_id: i64,
+    #[sqlx(flatten)]
+    rule: PermissionRule,
+}
+
+/// Settings of one guild, resolved from the database and the config file
+#[derive(Debug, Clone, Default)]
+pub struct GuildSettings {
+    pub mod_role_id: Option<serenity::RoleId>,
+    /// Channel where reports are sent to. `/report` replies with an error without it
+    pub reports_channel: Option<serenity::ChannelId>,
+    pub mod_log_channel: Option<serenity::ChannelId>,
+    pub message_log_channel: Option<serenity::ChannelId>,
+    pub member_log_channel: Option<serenity::ChannelId>,
+    /// Lowercased names of command categories which can't be used in this guild
+    pub disabled_categories: Vec<String>,
+}
+
+impl GuildSettings {
+    pub fn is_moderator(&self, roles: &[serenity::RoleId]) -> bool {
+        self.mod_role_id.is_some_and(|mod_role_id| roles.contains(&mod_role_id))
+    }
+
+    /// Mentions the moderator role, or just names the moderators if the guild has no such role
+    pub fn mod_role_mention(&self) -> String {
+        match self.mod_role_id {
+            Some(mod_role_id) => format!("<@&{}>", mod_role_id),
+            None => "moderators".to_owned(),
+        }
+    }
+
+    pub fn is_category_disabled(&self, category: &str) -> bool {
+        self.disabled_categories.iter().any(|disabled| disabled.eq_ignore_ascii_case(category))
+    }
+}
+
+/// Cache of the stored settings of all guilds, reloaded whenever they are changed
+#[derive(Debug, Clone)]
+pub struct GuildSettingsStore {
+    /// Guild whose unset settings fall back to the config file
+    home_guild_id: serenity::GuildId,
+    stored: Arc<RwLock<HashMap<serenity::GuildId, StoredGuildSettings>>>,
+    /// Rules configured with `?permissions`, checked before every command
+    permission_rules: Arc<RwLock<HashMap<serenity::GuildId, Vec<PermissionRule>>>>,
+}
+
+impl GuildSettingsStore {
+    pub async fn load(
+        database: &sqlx::SqlitePool,
+        home_guild_id: serenity::GuildId,
+    ) -> Result<Self, Error> {
+        let store = Self {
+            home_guild_id,
+            stored: Default::default(),
+            permission_rules: Default::default(),
+        };
+        store.reload(database).await?;
+        store.reload_permission_rules(database).await?;
+        Ok(store)
+    }
+
+    /// Whether unset settings of the guild fall back to the config file
+    pub fn is_home_guild(&self, guild_id: Option<serenity::GuildId>) -> bool {
+        guild_id == Some(self.home_guild_id)
+    }
+
+    pub async fn reload(&self, database: &sqlx::SqlitePool) -> Result<(), Error> {
+        let rows: Vec<StoredGuildSettings> =
+            sqlx::query_as("SELECT * FROM guild_settings").fetch_all(database).await?;
+
+        *self.stored.write().unwrap() = rows
+            .into_iter()
+            .map(|row| (serenity::GuildId::new(row.guild_id as u64), row))
+            .collect();
+        Ok(())
+    }
+
+    pub async fn reload_permission_rules(&self, database: &sqlx::SqlitePool) -> Result<(), Error> {
+        let rows: Vec<StoredPermissionRule> = sqlx::query_as(
+            "SELECT guild_id, id, target, role_id, permissions FROM command_permissions",
+        )
+        .fetch_all(database)
+        .await?;
+
+        let mut permission_rules = HashMap::<_, Vec<_>>::new();
+        for row in rows {
+            permission_rules
+                .entry(serenity::GuildId::new(row.guild_id as u64))
+                .or_default()
+                .push(row.rule);
+        }
+        *self.permission_rules.write().unwrap() = permission_rules;
+        Ok(())
+    }
+
+    /// Permission rules of the guild for a command or category
+    pub fn permission_rules(
+        &self,
+        guild_id: serenity::GuildId,
+        target: &str,
+    ) -> Vec<PermissionRule> {
+        self.permission_rules.read().unwrap().get(&guild_id).map_or_else(Vec::new, |rules| {
+            rules.iter().filter(|rule| rule.target == target).cloned().collect()
+        })
+    }
+
+    /// Settings of the guild. Outside of guilds, everything is unset
+    pub fn resolve(&self, config: &Config, guild_id: Option<serenity::GuildId>) -> GuildSettings {
+        let Some(guild_id) = guild_id else {
+            return GuildSettings::default();
+        };
+        let stored = self.stored.read().unwrap().get(&guild_id).cloned().unwrap_or_default();
+
+        let mut settings = GuildSettings {
+            mod_role_id: stored.mod_role_id.map(|id| serenity::RoleId::new(id as u64)),
+            reports_channel: stored.reports_channel.map(|id| serenity::ChannelId::new(id as u64)),
+            mod_log_channel: stored.mod_log_channel.map(|id| serenity::ChannelId::new(id as u64)),
+            message_log_channel: stored
+                .message_log_channel
+                .map(|id| serenity::ChannelId::new(id as u64)),
+            member_log_channel: stored
+                .member_log_channel
+                .map(|id| serenity::ChannelId::new(id as u64)),
+            disabled_categories: stored
+                .disabled_categories
+                .split(',')
+                .filter(|category| !category.is_empty())
+                .map(String::from)
+                .collect(),
+        };
+
+        // Role and channel IDs of the config file only exist in the home guild
+        if guild_id == self.home_guild_id {
+            settings.mod_role_id = settings.mod_role_id.or(config.mod_role_id);
+            settings.reports_channel = settings.reports_channel.or(config.reports_channel);
+            settings.mod_log_channel = settings.mod_log_channel.or(config.mod_log_channel);
+            settings.message_log_channel =
+                settings.message_log_channel.or(config.message_log_channel);
+            settings.member_log_channel = settings.member_log_channel.or(config.member_log_channel);
+        }
+
+        settings
+    }
+}
+
+/// Stores a single setting of the invoking guild and posts the change to the mod log
+async fn store_setting(
+    ctx: Context<'_>,
+    column: &'static str,
+    value: Option<i64>,
+    description: String,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    sqlx::query(&format!(
+        "INSERT INTO guild_settings (guild_id, {0}) VALUES (?, ?)
+         ON CONFLICT (guild_id) DO UPDATE SET {0} = excluded.{0}",
+        column
+    ))
+    .bind(guild_id.get() as i64)
+    .bind(value)
+    .execute(&ctx.data().database)
+    .await?;
+    ctx.data().guild_settings.reload(&ctx.data().database).await?;
+
+    ModLogEntry::from_context(ctx, "Setting changed")
+        .field("Setting", &description)
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(description).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum SettingsChannel {
+    #[name = "reports"]
+    Reports,
+    #[name = "mod_log"]
+    ModLog,
+    #[name = "message_log"]
+    MessageLog,
+    #[name = "member_log"]
+    MemberLog,
+}
+
+impl SettingsChannel {
+    fn column(self) -> &'static str {
+        match self {
+            Self::Reports => "reports_channel",
+            Self::ModLog => "mod_log_channel",
+            Self::MessageLog => "message_log_channel",
+            Self::MemberLog => "member_log_channel",
+        }
+    }
+}
+
+/// Configures the bot for this server (requires Manage Server)
+///
+/// Unset settings disable the feature, except in the bot's home server, where they fall back to \
+/// the config file.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    hide_in_help,
+    category = "Settings",
+    subcommands(
+        "settings_show",
+        "settings_modrole",
+        "settings_channel",
+        "settings_enable",
+        "settings_disable"
+    ),
+    subcommand_required,
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+pub async fn settings(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows the settings of this server
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "show",
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn settings_show(ctx: Context<'_>) -> Result<(), Error> {
+    let settings = ctx.data().settings(ctx.guild_id());
+
+    let channel = |channel_id: Option<serenity::ChannelId>| match channel_id {
+        Some(channel_id) => format!("<#{}>", channel_id),
+        None => "not set".to_owned(),
+    };
+    let disabled_categories = if settings.disabled_categories.is_empty() {
+        "none".to_owned()
+    } else {
+        settings.disabled_categories.join(", ")
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title("Server settings")
+        .field(
+            "Moderator role",
+            match settings.mod_role_id {
+                Some(mod_role_id) => format!("<@&{}>", mod_role_id),
+                None => "not set".to_owned(),
+            },
+            true,
+        )
+        .field("Reports channel", channel(settings.reports_channel), true)
+        .field("Mod log channel", channel(settings.mod_log_channel), true)
+        .field("Message log channel", channel(settings.message_log_channel), true)
+        .field("Member log channel", channel(settings.member_log_channel), true)
+        .field("Disabled categories", disabled_categories, true)
+        .color(crate::types::EMBED_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Sets the moderator role of this server. Leave out the role to unset it
+///
+/// ?settings modrole [@role]
+///
+/// In the bot's home server, unsetting the role makes the one of the config file apply again.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "modrole",
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn settings_modrole(
+    ctx: Context<'_>,
+    #[description = "Moderator role"] role: Option<serenity::Role>,
+) -> Result<(), Error> {
+    let description = match &role {
+        Some(role) => format!("Moderator role set to <@&{}>", role.id),
+        None if ctx.data().guild_settings.is_home_guild(ctx.guild_id()) => {
+            "Moderator role reset to the one of the config file".to_owned()
+        },
+        None => "Moderator role unset".to_owned(),
+    };
+    store_setting(ctx, "mod_role_id", role.map(|role| role.id.get() as i64), description).await
+}
+
+/// Sets one of the channels the bot posts to. Leave out the channel to unset it
+///
+/// ?settings channel <reports|mod_log|message_log|member_log> [#channel]
+///
+/// In the bot's home server, unsetting a channel makes the one of the config file apply again.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "channel",
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn settings_channel(
+    ctx: Context<'_>,
+    #[description = "Which channel to set"] kind: SettingsChannel,
+    #[description = "Channel"]
+    #[channel_types("Text")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    if channel.as_ref().is_some_and(|channel| Some(channel.guild_id) != ctx.guild_id()) {
+        anyhow::bail!("The channel must be in this server");
+    }
+
+    let description = match &channel {
+        Some(channel) => format!("{} channel set to <#{}>", kind.name(), channel.id),
+        None if ctx.data().guild_settings.is_home_guild(ctx.guild_id()) => {
+            format!("{} channel reset to the one of the config file", kind.name())
+        },
+        None => format!("{} channel unset", kind.name()),
+    };
+    let value = channel.map(|channel| channel.id.get() as i64);
+    store_setting(ctx, kind.column(), value, description).await
+}
+
+/// Makes sure the category exists and returns it lowercased
+fn resolve_category(ctx: Context<'_>, category: &str) -> Result<String, Error> {
+    let category = category.trim().to_lowercase();
+    let exists = ctx
+        .framework()
+        .options()
+        .commands
+        .iter()
+        .filter_map(|command| command.category.as_deref())
+        .any(|existing| existing.to_lowercase() == category);
+    if !exists {
+        anyhow::bail!("There is no command category `{}`", category);
+    }
+    Ok(category)
+}
+
+async fn store_disabled_categories(
+    ctx: Context<'_>,
+    disabled_categories: Vec<String>,
+    description: String,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    sqlx::query(
+        "INSERT INTO guild_settings (guild_id, disabled_categories) VALUES (?, ?)
+         ON CONFLICT (guild_id) DO UPDATE SET disabled_categories = excluded.disabled_categories",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(disabled_categories.join(","))
+    .execute(&ctx.data().database)
+    .await?;
+    ctx.data().guild_settings.reload(&ctx.data().database).await?;
+
+    ModLogEntry::from_context(ctx, "Setting changed")
+        .field("Setting", &description)
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(description).await?;
+
+    Ok(())
+}
+
+/// Allows using the commands of a category in this server again
+///
+/// ?settings enable <category>
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "enable",
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn settings_enable(
+    ctx: Context<'_>,
+    #[description = "Command category, e.g. Playground"] category: String,
+) -> Result<(), Error> {
+    let category = resolve_category(ctx, &category)?;
+
+    let mut disabled_categories = ctx.data().settings(ctx.guild_id()).disabled_categories;
+    if !disabled_categories.contains(&category) {
+        anyhow::bail!("The category `{}` isn't disabled", category);
+    }
+    disabled_categories.retain(|disabled| *disabled != category);
+
+    let description = format!("Enabled the `{}` commands", category);
+    store_disabled_categories(ctx, disabled_categories, description).await
+}
+
+/// Disables all commands of a category in this server
+///
+/// ?settings disable <category>
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "disable",
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn settings_disable(
+    ctx: Context<'_>,
+    #[description = "Command category, e.g. Playground"] category: String,
+) -> Result<(), Error> {
+    let category = resolve_category(ctx, &category)?;
+    if category == "settings" {
+        anyhow::bail!("The settings commands can't be disabled");
+    }
+
+    let mut disabled_categories = ctx.data().settings(ctx.guild_id()).disabled_categories;
+    if disabled_categories.contains(&category) {
+        anyhow::bail!("The category `{}` is already disabled", category);
+    }
+    disabled_categories.push(category.clone());
+
+    let description = format!("Disabled the `{}` commands", category);
+    store_disabled_categories(ctx, disabled_categories, description).await
+}
@@ -120,6 +120,63 @@ pub async fn trim_text(
     }
 }
 
+/// Cuts off text that doesn't fit into an embed field, whose values are limited to 1024
+/// characters and must not be empty
+pub fn truncate_for_field(text: &str) -> String {
+    const MAX_FIELD_LENGTH: usize = 1024;
+
+    if text.is_empty() {
+        return "_<empty>_".into();
+    }
+    if text.chars().count() <= MAX_FIELD_LENGTH {
+        return text.into();
+    }
+
+    let mut truncated = text.chars().take(MAX_FIELD_LENGTH - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+/// Whether @everyone is denied from viewing the channel, so only explicitly granted roles and
+/// members can see it
+pub fn is_private_channel(channel: &serenity::GuildChannel) -> bool {
+    let everyone = serenity::PermissionOverwriteType::Role(channel.guild_id.everyone_role());
+    channel.permission_overwrites.iter().any(|overwrite| {
+        overwrite.kind == everyone && overwrite.deny.contains(serenity::Permissions::VIEW_CHANNEL)
+    })
+}
+
+/// Downloads attachments so they can be uploaded again elsewhere, for example to preserve them if
+/// the original message is deleted. Returns the files, and a description of every attachment.
+/// Attachments that fail to download, or don't fit into one message together with the others, are
+/// described with a link instead
+pub async fn copy_attachments(
+    attachments: &[serenity::Attachment],
+) -> (Vec<serenity::CreateAttachment>, Vec<String>) {
+    // Stay below Discord's upload limit, which applies to all files of a message together
+    const MAX_COPIED_ATTACHMENTS_SIZE: u32 = 8 * 1024 * 1024;
+
+    let mut files = Vec::new();
+    let mut descriptions = Vec::new();
+    let mut total_size = 0;
+    for attachment in attachments {
+        if total_size + attachment.size <= MAX_COPIED_ATTACHMENTS_SIZE {
+            match attachment.download().await {
+                Ok(bytes) => {
+                    total_size += attachment.size;
+                    files.push(serenity::CreateAttachment::bytes(bytes, &attachment.filename));
+                    descriptions.push(format!("{} (attached below)", attachment.filename));
+                    continue;
+                },
+                Err(e) => warn!("Failed to download attachment {}: {}", attachment.filename, e),
+            }
+        }
+        descriptions.push(format!("[{}]({})", attachment.filename, attachment.url));
+    }
+
+    (files, descriptions)
+}
+
 pub async fn reply_potentially_long_text(
     ctx: Context<'_>,
     text_body: &str,
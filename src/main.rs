@@ -1,9 +1,16 @@
+mod automod;
 mod checks;
+mod config;
+mod cooldowns;
 mod crates;
 mod godbolt;
+mod guild_settings;
 mod helpers;
+mod member_log;
+mod message_log;
 mod misc;
 mod moderation;
+mod modmail;
 mod playground;
 mod types;
 
@@ -17,6 +24,9 @@ use poise::serenity_prelude as serenity;
 
 async fn on_error(error: poise::FrameworkError<'_, types::Data, Error>) {
     log::warn!("Encountered error: {:?}", error);
+    if let Some(ctx) = error.ctx() {
+        ctx.data().cooldowns.finish(ctx.id());
+    }
     if let poise::FrameworkError::ArgumentParse { error, ctx, .. } = error {
         let response = if error.is::<poise::CodeBlockError>() {
             "\
@@ -70,15 +80,26 @@ where
 
 async fn app() -> Result<(), Error> {
     let discord_token = env_var::<String>("DISCORD_TOKEN")?;
-    let mod_role_id = env_var("MOD_ROLE_ID")?;
-    let reports_channel = env_var("REPORTS_CHANNEL_ID").ok();
     let discord_guild_id = env_var("DISCORD_SERVER_ID")?;
+    let config_path =
+        env_var::<std::path::PathBuf>("CONFIG_FILE").unwrap_or_else(|_| "config.toml".into());
+    let config = config::SharedConfig::load(config_path)?;
+    let database_url =
+        env_var::<String>("DATABASE_URL").unwrap_or_else(|_| "sqlite:database.sqlite".to_owned());
+
+    let database = sqlx::SqlitePool::connect_with(
+        database_url.parse::<sqlx::sqlite::SqliteConnectOptions>()?.create_if_missing(true),
+    )
+    .await?;
+    sqlx::migrate!().run(&database).await?;
+    let guild_settings =
+        guild_settings::GuildSettingsStore::load(&database, discord_guild_id).await?;
 
     let intents = serenity::GatewayIntents::non_privileged()
         | serenity::GatewayIntents::GUILD_MEMBERS
         | serenity::GatewayIntents::MESSAGE_CONTENT;
 
-    let mut options = poise::FrameworkOptions {
+    let options = poise::FrameworkOptions {
         commands: vec![
             playground::play(),
             playground::playwarn(),
@@ -89,6 +110,12 @@ async fn app() -> Result<(), Error> {
             playground::fmt(),
             playground::microbench(),
             playground::procmacro(),
+            playground::asm(),
+            playground::mir(),
+            playground::hir(),
+            playground::play_llvmir(),
+            playground::test(),
+            playground::playground_health(),
             godbolt::play_cpp(),
             godbolt::godbolt(),
             godbolt::mca(),
@@ -101,6 +128,20 @@ async fn app() -> Result<(), Error> {
             moderation::cleanup(),
             moderation::move_(),
             moderation::slowmode(),
+            moderation::warn(),
+            moderation::cases(),
+            moderation::timeout(),
+            moderation::untimeout(),
+            moderation::tempban(),
+            moderation::lockdown(),
+            moderation::unlock(),
+            moderation::purge(),
+            moderation::permissions(),
+            moderation::report(),
+            moderation::report_message(),
+            automod::filter(),
+            automod::linkfilter(),
+            automod::raidmode(),
             misc::source(),
             misc::help(),
             misc::register(),
@@ -109,25 +150,18 @@ async fn app() -> Result<(), Error> {
             misc::revision(),
             misc::conradluget(),
             misc::nicosay(),
+            modmail::reply(),
+            modmail::areply(),
+            modmail::close(),
+            config::reload_config(),
+            guild_settings::settings(),
         ],
         prefix_options: poise::PrefixFrameworkOptions {
-            prefix: Some("?".into()),
-            additional_prefixes: vec![
-                poise::Prefix::Literal("cachyos_bot "),
-                poise::Prefix::Literal("🦀 "),
-                poise::Prefix::Literal("🦀"),
-                poise::Prefix::Literal("<:ferris:358652670585733120> "),
-                poise::Prefix::Literal("<:ferris:358652670585733120>"),
-                poise::Prefix::Literal("<:ferrisballSweat:678714352450142239> "),
-                poise::Prefix::Literal("<:ferrisballSweat:678714352450142239>"),
-                poise::Prefix::Literal("<:ferrisCat:1183779700485664820> "),
-                poise::Prefix::Literal("<:ferrisCat:1183779700485664820>"),
-                poise::Prefix::Literal("<:ferrisOwO:579331467000283136> "),
-                poise::Prefix::Literal("<:ferrisOwO:579331467000283136>"),
-                poise::Prefix::Regex(
-                    "(yo |hey )?(crab|ferris|fewwis),? can you (please |pwease )?".parse().unwrap(),
-                ),
-            ],
+            // The prefixes come from the config file, so that they can be changed on reload
+            prefix: None,
+            stripped_dynamic_prefix: Some(|_, message, data| {
+                Box::pin(config::strip_prefix(message, data))
+            }),
             edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
                 Duration::from_secs(60 * 5), // 5 minutes
             ))),
@@ -141,10 +175,19 @@ async fn app() -> Result<(), Error> {
         post_command: |ctx| {
             Box::pin(async move {
                 log::info!("Executed command {}!", ctx.command().qualified_name);
+                ctx.data().cooldowns.finish(ctx.id());
             })
         },
-        // Every command invocation must pass this check to continue execution
-        command_check: Some(|_ctx| Box::pin(async move { Ok(true) })),
+        // Every command invocation must pass this check to continue execution. Enforces the
+        // categories disabled with `?settings`, the permission rules configured with
+        // `?permissions` and the command cooldowns
+        command_check: Some(|ctx| {
+            Box::pin(async move {
+                Ok(checks::check_category_enabled(ctx).await?
+                    && checks::check_command_permissions(ctx).await?
+                    && cooldowns::check_cooldowns(ctx).await?)
+            })
+        }),
         // Enforce command checks even for owners (enforced by default)
         // Set to true to bypass checks, which is useful for testing
         skip_checks_for_owners: false,
@@ -156,37 +199,49 @@ async fn app() -> Result<(), Error> {
         ..Default::default()
     };
 
-    if reports_channel.is_some() {
-        options.commands.push(moderation::report());
-    }
-
     let framework =
         poise::Framework::builder()
             .setup(move |ctx, bot, _framework| {
                 Box::pin(async move {
                     let data = Data {
                         bot_user_id: bot.user.id,
-                        discord_guild_id,
-                        mod_role_id,
-                        reports_channel,
+                        config,
+                        guild_settings,
+                        modmail_locks: Default::default(),
                         bot_start_time: std::time::Instant::now(),
-                        http: reqwest::Client::new(),
+                        // Unreachable hosts fail quickly, so the playground fallback kicks in
+                        http: reqwest::Client::builder()
+                            .connect_timeout(std::time::Duration::from_secs(10))
+                            .build()?,
+                        database,
+                        message_cache: std::sync::Mutex::new(message_log::MessageCache::default()),
+                        automod: automod::Automod::default(),
+                        cooldowns: cooldowns::Cooldowns::default(),
                         godbolt_rust_targets: std::sync::Mutex::new(
                             godbolt::GodboltMetadata::default(),
                         ),
                         godbolt_cpp_targets: std::sync::Mutex::new(
                             godbolt::GodboltMetadata::default(),
                         ),
-                        active_slowmodes: std::sync::Mutex::new(std::collections::HashMap::new()),
                     };
 
-                    // log::debug!("Registering commands...");
-                    // poise::builtins::register_in_guild(
-                    // ctx,
-                    // &framework.options().commands,
-                    // data.discord_guild_id,
-                    // )
-                    // .await?;
+                    log::debug!("Loading automod filters");
+                    *data.automod.filters.write().unwrap() =
+                        automod::load_filters(&data.database).await?;
+                    *data.automod.link_filter_disabled_channels.write().unwrap() =
+                        automod::load_link_filter_disabled_channels(&data.database).await?;
+
+                    log::debug!("Starting moderation scheduler");
+                    tokio::spawn(
+                        moderation::Scheduler {
+                            http: ctx.http.clone(),
+                            database: data.database.clone(),
+                            bot_user_id: data.bot_user_id,
+                            config: data.config.clone(),
+                            guild_settings: data.guild_settings.clone(),
+                        }
+                        .run(),
+                    );
 
                     log::debug!("Setting activity text");
                     ctx.set_activity(Some(serenity::ActivityData::listening("?help")));
@@ -208,12 +263,60 @@ async fn app() -> Result<(), Error> {
 }
 
 async fn event_handler(
-    _ctx: &serenity::Context,
+    ctx: &serenity::Context,
     event: &serenity::FullEvent,
-    _data: &Data,
+    data: &Data,
 ) -> Result<(), Error> {
     log::debug!("Got an event in event handler: {:?}", event.snake_case_name());
 
+    match event {
+        serenity::FullEvent::InteractionCreate {
+            interaction: serenity::Interaction::Component(interaction),
+        } => {
+            moderation::on_report_button(ctx, data, interaction).await?;
+        },
+        serenity::FullEvent::Message { new_message } => {
+            message_log::on_message(data, new_message);
+            automod::on_message(ctx, data, new_message).await?;
+            modmail::on_direct_message(ctx, data, new_message).await?;
+        },
+        serenity::FullEvent::MessageUpdate { event, .. } => {
+            message_log::on_message_update(ctx, data, event).await?;
+            automod::on_message_update(ctx, data, event).await?;
+        },
+        serenity::FullEvent::MessageDelete { deleted_message_id, .. } => {
+            message_log::on_message_delete(ctx, data, *deleted_message_id).await?;
+        },
+        serenity::FullEvent::MessageDeleteBulk {
+            channel_id,
+            multiple_deleted_messages_ids,
+            ..
+        } => {
+            message_log::on_message_delete_bulk(
+                ctx,
+                data,
+                *channel_id,
+                multiple_deleted_messages_ids,
+            )
+            .await?;
+        },
+        serenity::FullEvent::GuildMemberAddition { new_member } => {
+            member_log::on_member_join(ctx, data, new_member).await?;
+            automod::on_member_join(ctx, data, new_member).await?;
+        },
+        serenity::FullEvent::GuildMemberRemoval { guild_id, user, member_data_if_available } => {
+            member_log::on_member_leave(
+                ctx,
+                data,
+                *guild_id,
+                user,
+                member_data_if_available.as_ref(),
+            )
+            .await?;
+        },
+        _ => {},
+    }
+
     Ok(())
 }
 
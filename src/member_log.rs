@@ -0,0 +1,81 @@
+//! Log members joining and leaving to a channel, highlighting freshly created accounts to help
+//! spot raid accounts and ban evaders
+
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+
+use crate::types::Data;
+
+/// Embed color for accounts younger than the configured threshold
+const NEW_ACCOUNT_COLOR: (u8, u8, u8) = (0xe0, 0x2b, 0x2b);
+
+/// Builds the part of the embed common to joins and leaves
+fn member_embed(data: &Data, title: &str, user: &serenity::User) -> serenity::CreateEmbed {
+    let created_at = user.created_at();
+    let account_age = chrono::Utc::now() - *created_at;
+    let new_account_threshold = data.config.get().new_account_threshold;
+    let is_new_account = account_age < new_account_threshold.0;
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title(title)
+        .thumbnail(user.face())
+        .description(format!("<@{}> {} ({})", user.id, user.tag(), user.id))
+        .field(
+            "Account created",
+            format!("<t:{0}:f> (<t:{0}:R>)", created_at.unix_timestamp()),
+            true,
+        )
+        .timestamp(serenity::Timestamp::now());
+
+    if is_new_account {
+        embed = embed
+            .field("⚠️ New account", format!("Younger than {}", new_account_threshold), true)
+            .color(NEW_ACCOUNT_COLOR);
+    } else {
+        embed = embed.color(crate::types::EMBED_COLOR);
+    }
+
+    embed
+}
+
+pub async fn on_member_join(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    let Some(log_channel) = data.settings(member.guild_id).member_log_channel else {
+        return Ok(());
+    };
+
+    let embed = member_embed(data, "Member joined", &member.user);
+
+    log_channel.send_message(ctx, serenity::CreateMessage::new().embed(embed)).await?;
+
+    Ok(())
+}
+
+pub async fn on_member_leave(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    user: &serenity::User,
+    member: Option<&serenity::Member>,
+) -> Result<(), Error> {
+    let Some(log_channel) = data.settings(guild_id).member_log_channel else {
+        return Ok(());
+    };
+
+    let mut embed = member_embed(data, "Member left", user);
+    // Only available if the member was cached
+    if let Some(joined_at) = member.and_then(|member| member.joined_at) {
+        embed = embed.field(
+            "Joined",
+            format!("<t:{0}:f> (<t:{0}:R>)", joined_at.unix_timestamp()),
+            true,
+        );
+    }
+
+    log_channel.send_message(ctx, serenity::CreateMessage::new().embed(embed)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,217 @@
+//! Log edited and deleted messages to a channel, so moderators can see what a rule-breaker
+//! removed
+
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+
+use crate::helpers::truncate_for_field;
+use crate::types::Data;
+
+/// How many recent messages are kept in memory to be able to show their content after an edit
+/// or deletion
+const MAX_CACHED_MESSAGES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct CachedMessage {
+    pub guild_id: serenity::GuildId,
+    pub channel_id: serenity::ChannelId,
+    pub author_id: serenity::UserId,
+    pub content: String,
+    pub attachments: Vec<String>,
+    pub timestamp: serenity::Timestamp,
+}
+
+/// Bounded store of recent messages. Once full, the oldest messages are evicted first
+#[derive(Debug, Default)]
+pub struct MessageCache {
+    messages: HashMap<serenity::MessageId, CachedMessage>,
+    insertion_order: VecDeque<serenity::MessageId>,
+}
+
+impl MessageCache {
+    pub fn insert(&mut self, message_id: serenity::MessageId, message: CachedMessage) {
+        if self.messages.insert(message_id, message).is_none() {
+            self.insertion_order.push_back(message_id);
+        }
+
+        while self.insertion_order.len() > MAX_CACHED_MESSAGES {
+            if let Some(oldest_message_id) = self.insertion_order.pop_front() {
+                self.messages.remove(&oldest_message_id);
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, message_id: serenity::MessageId) -> Option<&mut CachedMessage> {
+        self.messages.get_mut(&message_id)
+    }
+
+    /// The message ID stays in the insertion order queue until it's evicted, which is harmless
+    pub fn remove(&mut self, message_id: serenity::MessageId) -> Option<CachedMessage> {
+        self.messages.remove(&message_id)
+    }
+}
+
+fn message_link(
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+    message_id: serenity::MessageId,
+) -> String {
+    format!("https://discord.com/channels/{}/{}/{}", guild_id, channel_id, message_id)
+}
+
+fn should_log(
+    data: &Data,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+    author: &serenity::User,
+) -> bool {
+    !author.bot
+        && data
+            .settings(guild_id)
+            .message_log_channel
+            .is_some_and(|log_channel| log_channel != channel_id)
+}
+
+pub fn on_message(data: &Data, message: &serenity::Message) {
+    let Some(guild_id) = message.guild_id else {
+        return;
+    };
+    if !should_log(data, guild_id, message.channel_id, &message.author) {
+        return;
+    }
+
+    data.message_cache.lock().unwrap().insert(message.id, CachedMessage {
+        guild_id,
+        channel_id: message.channel_id,
+        author_id: message.author.id,
+        content: message.content.clone(),
+        attachments: message.attachments.iter().map(|a| a.url.clone()).collect(),
+        timestamp: message.timestamp,
+    });
+}
+
+pub async fn on_message_update(
+    ctx: &serenity::Context,
+    data: &Data,
+    event: &serenity::MessageUpdateEvent,
+) -> Result<(), Error> {
+    // Embed-only updates, for example when Discord generates link previews, don't have content
+    let Some(new_content) = &event.content else {
+        return Ok(());
+    };
+
+    let old_message = {
+        let mut message_cache = data.message_cache.lock().unwrap();
+        let Some(cached_message) = message_cache.get_mut(event.id) else {
+            return Ok(());
+        };
+        if cached_message.content == *new_content {
+            return Ok(());
+        }
+        let old_message = cached_message.clone();
+        cached_message.content = new_content.clone();
+        old_message
+    };
+    // The log channel may have been unset since the message was cached
+    let Some(log_channel) = data.settings(old_message.guild_id).message_log_channel else {
+        return Ok(());
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title("Message edited")
+        .description(format!(
+            "<@{}> edited a message in <#{}>\n{}",
+            old_message.author_id,
+            old_message.channel_id,
+            message_link(old_message.guild_id, old_message.channel_id, event.id)
+        ))
+        .field("Before", truncate_for_field(&old_message.content), false)
+        .field("After", truncate_for_field(new_content), false)
+        .timestamp(event.edited_timestamp.unwrap_or_else(serenity::Timestamp::now))
+        .color(crate::types::EMBED_COLOR);
+
+    log_channel.send_message(ctx, serenity::CreateMessage::new().embed(embed)).await?;
+
+    Ok(())
+}
+
+pub async fn on_message_delete(
+    ctx: &serenity::Context,
+    data: &Data,
+    message_id: serenity::MessageId,
+) -> Result<(), Error> {
+    let Some(message) = data.message_cache.lock().unwrap().remove(message_id) else {
+        return Ok(());
+    };
+    let Some(log_channel) = data.settings(message.guild_id).message_log_channel else {
+        return Ok(());
+    };
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title("Message deleted")
+        .description(format!(
+            "Message by <@{}> was deleted in <#{}>",
+            message.author_id, message.channel_id
+        ))
+        .field("Content", truncate_for_field(&message.content), false)
+        .field("Sent", format!("<t:{}:f>", message.timestamp.unix_timestamp()), true)
+        .timestamp(serenity::Timestamp::now())
+        .color(crate::types::EMBED_COLOR);
+    if !message.attachments.is_empty() {
+        embed =
+            embed.field("Attachments", truncate_for_field(&message.attachments.join("\n")), false);
+    }
+
+    log_channel.send_message(ctx, serenity::CreateMessage::new().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Bulk deletions can contain up to 100 messages, so their content is sent as a text file
+pub async fn on_message_delete_bulk(
+    ctx: &serenity::Context,
+    data: &Data,
+    channel_id: serenity::ChannelId,
+    message_ids: &[serenity::MessageId],
+) -> Result<(), Error> {
+    let mut messages = {
+        let mut message_cache = data.message_cache.lock().unwrap();
+        message_ids.iter().filter_map(|&id| message_cache.remove(id)).collect::<Vec<_>>()
+    };
+    if messages.is_empty() {
+        return Ok(());
+    }
+    messages.sort_by_key(|message| message.timestamp);
+    let Some(log_channel) = data.settings(messages[0].guild_id).message_log_channel else {
+        return Ok(());
+    };
+
+    let mut transcript = String::new();
+    for message in &messages {
+        transcript +=
+            &format!("[{}] {}: {}\n", message.timestamp, message.author_id, message.content);
+        for attachment in &message.attachments {
+            transcript += &format!("    attachment: {}\n", attachment);
+        }
+    }
+
+    let embed = serenity::CreateEmbed::new()
+        .title("Messages bulk deleted")
+        .description(format!(
+            "{} messages were deleted in <#{}> ({} of them with known content)",
+            message_ids.len(),
+            channel_id,
+            messages.len()
+        ))
+        .timestamp(serenity::Timestamp::now())
+        .color(crate::types::EMBED_COLOR);
+    let builder = serenity::CreateMessage::new().embed(embed).add_file(
+        serenity::CreateAttachment::bytes(transcript.into_bytes(), "deleted_messages.txt"),
+    );
+
+    log_channel.send_message(ctx, builder).await?;
+
+    Ok(())
+}
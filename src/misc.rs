@@ -38,12 +38,7 @@ You can edit your message to the bot and the bot will edit its response.";
 /// Register slash commands in this guild or globally
 ///
 /// Run with no arguments to register in guild, run with argument "global" to register globally.
-#[poise::command(
-    prefix_command,
-    hide_in_help,
-    category = "Miscellaneous",
-    check = "crate::checks::check_is_moderator"
-)]
+#[poise::command(prefix_command, hide_in_help, category = "Miscellaneous")]
 pub async fn register(ctx: Context<'_>, #[flag] global: bool) -> Result<(), Error> {
     poise::builtins::register_application_commands(ctx, global).await?;
 
@@ -0,0 +1,180 @@
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+
+use super::{Duration, ModLogEntry};
+use crate::types::Context;
+
+/// How many cases `?cases` lists at most. Discord embeds can't have more than 25 fields
+const MAX_LISTED_CASES: i64 = 25;
+/// How many characters the case fields of `?cases` may have in total. Discord embeds can't have
+/// more than 6000, and some are left for the title and footer
+const MAX_CASE_FIELDS_LENGTH: usize = 5800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum CaseKind {
+    Warn,
+    Timeout,
+    Untimeout,
+    Tempban,
+    Unban,
+}
+
+impl CaseKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Warn => "Warning",
+            Self::Timeout => "Timeout",
+            Self::Untimeout => "Timeout lifted",
+            Self::Tempban => "Temporary ban",
+            Self::Unban => "Unban",
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Case {
+    pub id: i64,
+    pub kind: CaseKind,
+    pub moderator_id: i64,
+    pub reason: Option<String>,
+    pub created_at: i64,
+    pub duration: Option<i64>,
+}
+
+/// Stores a moderation action in the database and returns its case number
+pub async fn record_case(
+    database: &sqlx::SqlitePool,
+    guild_id: serenity::GuildId,
+    kind: CaseKind,
+    user_id: serenity::UserId,
+    moderator_id: serenity::UserId,
+    reason: Option<&str>,
+    duration: Option<Duration>,
+) -> Result<i64, Error> {
+    let case_id = sqlx::query_scalar(
+        "INSERT INTO cases (guild_id, kind, user_id, moderator_id, reason, created_at, duration)
+         VALUES (?, ?, ?, ?, ?, ?, ?)
+         RETURNING id",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(kind)
+    .bind(user_id.get() as i64)
+    .bind(moderator_id.get() as i64)
+    .bind(reason)
+    .bind(chrono::Utc::now().timestamp())
+    .bind(duration.map(|duration| duration.0.num_seconds()))
+    .fetch_one(database)
+    .await?;
+
+    log::info!("Recorded case #{}: {:?} of {} by {}", case_id, kind, user_id, moderator_id);
+
+    Ok(case_id)
+}
+
+/// Warns a user and records it in their moderation history (moderator only)
+///
+/// The warning is stored as a numbered case which can be looked up later with `?cases`.
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Moderation")]
+pub async fn warn(
+    ctx: Context<'_>,
+    #[description = "User to warn"] user: serenity::User,
+    #[description = "Why the user is warned"]
+    #[rest]
+    reason: String,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    let case_id = record_case(
+        &ctx.data().database,
+        guild_id,
+        CaseKind::Warn,
+        user.id,
+        ctx.author().id,
+        Some(&reason),
+        None,
+    )
+    .await?;
+
+    ModLogEntry::from_context(ctx, "Warning")
+        .target(user.id)
+        .case(case_id)
+        .reason(Some(&reason))
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(format!("Case #{}: warned <@{}>\n> {}", case_id, user.id.get(), reason)).await?;
+
+    Ok(())
+}
+
+/// Lists the moderation history of a user (moderator only)
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Moderation")]
+pub async fn cases(
+    ctx: Context<'_>,
+    #[description = "User whose moderation history to show"] user: serenity::User,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    let total_cases: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM cases WHERE guild_id = ? AND user_id = ?")
+            .bind(guild_id.get() as i64)
+            .bind(user.id.get() as i64)
+            .fetch_one(&ctx.data().database)
+            .await?;
+
+    let cases: Vec<Case> = sqlx::query_as(
+        "SELECT id, kind, moderator_id, reason, created_at, duration FROM cases
+         WHERE guild_id = ? AND user_id = ?
+         ORDER BY id DESC
+         LIMIT ?",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(user.id.get() as i64)
+    .bind(MAX_LISTED_CASES)
+    .fetch_all(&ctx.data().database)
+    .await?;
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("Cases for {}", user.tag()))
+        .color(crate::types::EMBED_COLOR);
+
+    if cases.is_empty() {
+        embed = embed.description("_No cases recorded_");
+    }
+
+    let mut fields = Vec::new();
+    let mut embed_length = 0;
+    for case in cases {
+        let mut title = format!("#{} · {}", case.id, case.kind.label());
+        if let Some(duration) = case.duration {
+            title += &format!(" ({})", Duration::from_secs(duration));
+        }
+        let value = crate::helpers::truncate_for_field(&format!(
+            "<t:{}:R> by <@{}>\n{}",
+            case.created_at,
+            case.moderator_id,
+            case.reason.as_deref().unwrap_or("_no reason given_")
+        ));
+
+        embed_length += title.chars().count() + value.chars().count();
+        if embed_length > MAX_CASE_FIELDS_LENGTH {
+            break;
+        }
+        fields.push((title, value, false));
+    }
+    if (fields.len() as i64) < total_cases {
+        embed = embed.footer(serenity::CreateEmbedFooter::new(format!(
+            "Showing the latest {} of {} cases",
+            fields.len(),
+            total_cases
+        )));
+    }
+    embed = embed.fields(fields);
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use std::str::FromStr;
+
+/// A human-readable duration argument like `90s`, `30m`, `2h` or `1d12h`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Duration(pub chrono::Duration);
+
+impl Duration {
+    pub fn from_secs(seconds: i64) -> Self {
+        Self(chrono::Duration::seconds(seconds))
+    }
+}
+
+/// Poise requires argument parse errors to implement [`std::error::Error`], which
+/// [`anyhow::Error`] doesn't
+#[derive(Debug)]
+pub struct DurationParseError(String);
+
+impl std::fmt::Display for DurationParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DurationParseError {}
+
+impl FromStr for Duration {
+    type Err = DurationParseError;
+
+    fn from_str(s: &str) -> Result<Self, DurationParseError> {
+        let invalid = || {
+            DurationParseError(format!(
+                "invalid duration `{}`, try something like `30m`, `2h` or `1d12h`",
+                s
+            ))
+        };
+        let too_long = || DurationParseError(format!("duration `{}` is too long", s));
+
+        let mut total_seconds: i64 = 0;
+        let mut number = String::new();
+        for c in s.trim().chars() {
+            // Parts may be separated by spaces, like in the output of `Display`
+            if c.is_whitespace() && number.is_empty() {
+                continue;
+            }
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+
+            let unit_seconds = match c.to_ascii_lowercase() {
+                's' => 1,
+                'm' => 60,
+                'h' => 60 * 60,
+                'd' => 60 * 60 * 24,
+                'w' => 60 * 60 * 24 * 7,
+                _ => {
+                    return Err(DurationParseError(format!(
+                        "invalid duration unit `{}`, expected one of s, m, h, d or w",
+                        c
+                    )))
+                },
+            };
+            let amount: i64 = number.parse().map_err(|_| invalid())?;
+            number.clear();
+
+            total_seconds = amount
+                .checked_mul(unit_seconds)
+                .and_then(|seconds| total_seconds.checked_add(seconds))
+                .ok_or_else(too_long)?;
+        }
+
+        // A trailing number without unit or an empty string
+        if !number.is_empty() || total_seconds == 0 {
+            return Err(invalid());
+        }
+
+        chrono::Duration::try_seconds(total_seconds).map(Self).ok_or_else(too_long)
+    }
+}
+
+impl std::fmt::Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let div_mod = |a, b| (a / b, a % b);
+
+        let (minutes, seconds) = div_mod(self.0.num_seconds(), 60);
+        let (hours, minutes) = div_mod(minutes, 60);
+        let (days, hours) = div_mod(hours, 24);
+
+        let mut parts = Vec::new();
+        for (amount, unit) in [(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s")] {
+            if amount != 0 {
+                parts.push(format!("{}{}", amount, unit));
+            }
+        }
+
+        if parts.is_empty() {
+            f.write_str("0s")
+        } else {
+            f.write_str(&parts.join(" "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(s: &str) -> Result<i64, DurationParseError> {
+        s.parse::<Duration>().map(|duration| duration.0.num_seconds())
+    }
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(seconds("90s").unwrap(), 90);
+        assert_eq!(seconds("30m").unwrap(), 30 * 60);
+        assert_eq!(seconds("2H").unwrap(), 2 * 60 * 60);
+        assert_eq!(seconds("7d").unwrap(), 7 * 24 * 60 * 60);
+        assert_eq!(seconds("1w").unwrap(), 7 * 24 * 60 * 60);
+    }
+
+    #[test]
+    fn parses_mixed_units() {
+        assert_eq!(seconds("1d12h").unwrap(), 36 * 60 * 60);
+        assert_eq!(seconds(" 1h 30m ").unwrap(), 90 * 60);
+        assert_eq!(seconds("30m1h").unwrap(), 90 * 60);
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for invalid in ["", " ", "0s", "30", "1h30", "m", "1x", "-5m", "1 h", "1.5h"] {
+            assert!(seconds(invalid).is_err(), "`{}` should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert!(seconds("9999999999999999999s").is_err());
+        assert!(seconds("9999999999999999w").is_err());
+        assert!(seconds("9223372036854775807s1s").is_err());
+    }
+
+    #[test]
+    fn displays_parts() {
+        assert_eq!(Duration::from_secs(0).to_string(), "0s");
+        assert_eq!(Duration::from_secs(90).to_string(), "1m 30s");
+        assert_eq!(Duration::from_secs(36 * 60 * 60).to_string(), "1d 12h");
+        assert_eq!(Duration::from_secs(8 * 24 * 60 * 60 + 1).to_string(), "8d 1s");
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for seconds in [1, 59, 60, 61, 3600, 3661, 86400, 90061, 1_000_000] {
+            let duration = Duration::from_secs(seconds);
+            assert_eq!(duration.to_string().parse::<Duration>().unwrap(), duration);
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::str::FromStr;
+
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+
+use super::{Duration, ModLogEntry};
+use crate::types::{ActiveLockdown, Context};
+
+/// Permissions denied to @everyone during a lockdown
+const LOCKED_PERMISSIONS: serenity::Permissions =
+    serenity::Permissions::SEND_MESSAGES.union(serenity::Permissions::SEND_MESSAGES_IN_THREADS);
+
+/// Which channels `?lockdown` and `?unlock` act on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockdownTarget {
+    Channel(serenity::ChannelId),
+    /// All public text channels of the guild
+    All,
+}
+
+#[derive(Debug)]
+pub struct LockdownTargetParseError(String);
+
+impl std::fmt::Display for LockdownTargetParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid target `{}`, expected a channel or `all`", self.0)
+    }
+}
+
+impl std::error::Error for LockdownTargetParseError {}
+
+impl FromStr for LockdownTarget {
+    type Err = LockdownTargetParseError;
+
+    fn from_str(s: &str) -> Result<Self, LockdownTargetParseError> {
+        if s.eq_ignore_ascii_case("all") {
+            return Ok(Self::All);
+        }
+
+        let channel_id = s.strip_prefix("<#").and_then(|s| s.strip_suffix('>')).unwrap_or(s);
+        match channel_id.parse::<u64>() {
+            Ok(channel_id) if channel_id != 0 => {
+                Ok(Self::Channel(serenity::ChannelId::new(channel_id)))
+            },
+            _ => Err(LockdownTargetParseError(s.to_owned())),
+        }
+    }
+}
+
+/// Resolves the target to the channels it refers to. Defaults to the channel of the invocation
+async fn target_channels(
+    ctx: Context<'_>,
+    guild_id: serenity::GuildId,
+    target: Option<LockdownTarget>,
+) -> Result<Vec<serenity::GuildChannel>, Error> {
+    let channel_id = match target.unwrap_or(LockdownTarget::Channel(ctx.channel_id())) {
+        LockdownTarget::Channel(channel_id) => channel_id,
+        LockdownTarget::All => {
+            return Ok(guild_id
+                .channels(ctx)
+                .await?
+                .into_values()
+                .filter(|channel| {
+                    channel.kind == serenity::ChannelType::Text
+                        && !crate::helpers::is_private_channel(channel)
+                })
+                .collect());
+        },
+    };
+
+    let channel = channel_id
+        .to_channel(ctx)
+        .await?
+        .guild()
+        .filter(|channel| channel.guild_id == guild_id)
+        .ok_or(anyhow::anyhow!("Can't lock down channels outside this server"))?;
+    Ok(vec![channel])
+}
+
+/// Registers the lockdown, then denies @everyone from sending messages in the channel
+async fn lock_channel(
+    ctx: Context<'_>,
+    channel: &serenity::GuildChannel,
+    invocation_time: i64,
+    expires_at: Option<i64>,
+) -> Result<(), Error> {
+    let everyone = serenity::PermissionOverwriteType::Role(channel.guild_id.everyone_role());
+    let current_overwrite =
+        channel.permission_overwrites.iter().find(|overwrite| overwrite.kind == everyone);
+
+    // If we're overwriting an existing lockdown, the channel's current overwrite is not the
+    // original one, so the existing entry's previous permissions are kept
+    sqlx::query(
+        "INSERT INTO active_lockdowns
+         (channel_id, guild_id, previous_allow, previous_deny, invocation_time, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT (channel_id) DO UPDATE SET
+         invocation_time = excluded.invocation_time,
+         expires_at = excluded.expires_at",
+    )
+    .bind(channel.id.get() as i64)
+    .bind(channel.guild_id.get() as i64)
+    .bind(current_overwrite.map(|overwrite| overwrite.allow.bits() as i64))
+    .bind(current_overwrite.map(|overwrite| overwrite.deny.bits() as i64))
+    .bind(invocation_time)
+    .bind(expires_at)
+    .execute(&ctx.data().database)
+    .await?;
+
+    let (allow, deny) = current_overwrite.map_or_else(
+        || (serenity::Permissions::empty(), serenity::Permissions::empty()),
+        |overwrite| (overwrite.allow, overwrite.deny),
+    );
+    let overwrite = serenity::PermissionOverwrite {
+        allow: allow - LOCKED_PERMISSIONS,
+        deny: deny | LOCKED_PERMISSIONS,
+        kind: everyone,
+    };
+    channel.id.create_permission(ctx, overwrite).await?;
+
+    Ok(())
+}
+
+/// Puts the @everyone overwrite back into the exact state from before the lockdown
+async fn restore_permissions(
+    http: impl AsRef<serenity::Http>,
+    lockdown: &ActiveLockdown,
+) -> Result<(), serenity::Error> {
+    let channel_id = serenity::ChannelId::new(lockdown.channel_id as u64);
+    let everyone = serenity::PermissionOverwriteType::Role(
+        serenity::GuildId::new(lockdown.guild_id as u64).everyone_role(),
+    );
+
+    match (lockdown.previous_allow, lockdown.previous_deny) {
+        (Some(allow), Some(deny)) => {
+            let overwrite = serenity::PermissionOverwrite {
+                allow: serenity::Permissions::from_bits_truncate(allow as u64),
+                deny: serenity::Permissions::from_bits_truncate(deny as u64),
+                kind: everyone,
+            };
+            channel_id.create_permission(http, overwrite).await
+        },
+        _ => channel_id.delete_permission(http, everyone).await,
+    }
+}
+
+/// Restores the permissions of all lockdowns which have run out, including those that ran out
+/// while the bot was offline
+pub(super) async fn lift_expired_lockdowns(scheduler: &super::Scheduler) -> Result<(), Error> {
+    let expired_lockdowns: Vec<ActiveLockdown> = sqlx::query_as(
+        "SELECT * FROM active_lockdowns WHERE expires_at IS NOT NULL AND expires_at <= ?",
+    )
+    .bind(chrono::Utc::now().timestamp())
+    .fetch_all(&scheduler.database)
+    .await?;
+
+    for lockdown in expired_lockdowns {
+        let channel_id = serenity::ChannelId::new(lockdown.channel_id as u64);
+        log::info!("Lifting lockdown of {}", channel_id);
+
+        match restore_permissions(&scheduler.http, &lockdown).await {
+            Ok(()) => {
+                ModLogEntry::new("Lockdown expired", scheduler.bot_user_id)
+                    .channel(channel_id)
+                    .post(
+                        &scheduler.http,
+                        scheduler.mod_log_channel(serenity::GuildId::new(lockdown.guild_id as u64)),
+                    )
+                    .await;
+            },
+            // The channel has been deleted
+            Err(serenity::Error::Http(e)) if e.status_code().map(|s| s.as_u16()) == Some(404) => {
+                log::info!("Channel {} with active lockdown doesn't exist anymore", channel_id);
+            },
+            // Keep the entry around so that unlocking is retried
+            Err(e) => {
+                log::warn!("Failed to lift lockdown of {}: {}", channel_id, e);
+                continue;
+            },
+        }
+
+        // If the invocation time differs, this lockdown has been overwritten in the meantime and
+        // the new one must stay
+        sqlx::query("DELETE FROM active_lockdowns WHERE channel_id = ? AND invocation_time = ?")
+            .bind(lockdown.channel_id)
+            .bind(lockdown.invocation_time)
+            .execute(&scheduler.database)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Prevents @everyone from sending messages in a channel (moderator only)
+///
+/// ?lockdown [channel|all] [duration]
+///
+/// Locks down the current channel by default, or all public text channels with `all`. Without \
+/// a duration, the lockdown lasts until `?unlock`. The previous permissions are stored in the \
+/// database and restored exactly on unlock, even if the bot restarts in the meantime. Locking \
+/// down a channel again overwrites the running lockdown's duration.
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Moderation")]
+pub async fn lockdown(
+    ctx: Context<'_>,
+    #[description = "Channel to lock down, or `all` for all public channels"] target: Option<
+        LockdownTarget,
+    >,
+    #[description = "How long the lockdown lasts, e.g. 30m or 2h"] duration: Option<Duration>,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+    ctx.defer().await?;
+
+    let channels = target_channels(ctx, guild_id, target).await?;
+    let invocation_time = ctx.created_at().timestamp_millis();
+    let expires_at =
+        duration.map(|duration| ctx.created_at().timestamp() + duration.0.num_seconds());
+
+    let mut locked_channels = Vec::new();
+    for channel in &channels {
+        match lock_channel(ctx, channel, invocation_time, expires_at).await {
+            Ok(()) => locked_channels.push(format!("<#{}>", channel.id)),
+            Err(e) => log::warn!("Failed to lock down {}: {}", channel.id, e),
+        }
+    }
+    if locked_channels.is_empty() {
+        anyhow::bail!("No channel could be locked down");
+    }
+
+    let until = match expires_at {
+        Some(expires_at) => format!("until <t:{}:f>", expires_at),
+        None => "until unlocked".to_owned(),
+    };
+    ModLogEntry::from_context(ctx, "Lockdown")
+        .field("Locked channels", locked_channels.join(" "))
+        .field("Duration", duration.map_or("Until unlocked".to_owned(), |d| d.to_string()))
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(format!("Locked down {} {}", locked_channels.join(" "), until)).await?;
+
+    Ok(())
+}
+
+/// Lifts a lockdown, restoring the previous permissions (moderator only)
+///
+/// ?unlock [channel|all]
+///
+/// Unlocks the current channel by default, or all locked down channels with `all`.
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Moderation")]
+pub async fn unlock(
+    ctx: Context<'_>,
+    #[description = "Channel to unlock, or `all` for all locked down channels"] target: Option<
+        LockdownTarget,
+    >,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+    ctx.defer().await?;
+
+    let target = target.unwrap_or(LockdownTarget::Channel(ctx.channel_id()));
+    let lockdowns: Vec<ActiveLockdown> = match target {
+        LockdownTarget::Channel(channel_id) => {
+            sqlx::query_as("SELECT * FROM active_lockdowns WHERE channel_id = ? AND guild_id = ?")
+                .bind(channel_id.get() as i64)
+                .bind(guild_id.get() as i64)
+                .fetch_all(&ctx.data().database)
+                .await?
+        },
+        LockdownTarget::All => {
+            sqlx::query_as("SELECT * FROM active_lockdowns WHERE guild_id = ?")
+                .bind(guild_id.get() as i64)
+                .fetch_all(&ctx.data().database)
+                .await?
+        },
+    };
+    if lockdowns.is_empty() {
+        ctx.say("There is no lockdown currently active").await?;
+        return Ok(());
+    }
+
+    let mut unlocked_channels = Vec::new();
+    let mut failed_channels = Vec::new();
+    for lockdown in &lockdowns {
+        // The entry is only removed once the permissions are restored, so that the previous
+        // permissions aren't lost if restoring fails
+        match restore_permissions(ctx, lockdown).await {
+            Ok(()) => unlocked_channels.push(format!("<#{}>", lockdown.channel_id)),
+            Err(e) => {
+                log::warn!("Failed to unlock {}: {}", lockdown.channel_id, e);
+                failed_channels.push(format!("<#{}>", lockdown.channel_id));
+                continue;
+            },
+        }
+
+        sqlx::query("DELETE FROM active_lockdowns WHERE channel_id = ? AND invocation_time = ?")
+            .bind(lockdown.channel_id)
+            .bind(lockdown.invocation_time)
+            .execute(&ctx.data().database)
+            .await?;
+    }
+    if unlocked_channels.is_empty() {
+        anyhow::bail!("Failed to unlock {}", failed_channels.join(" "));
+    }
+
+    ModLogEntry::from_context(ctx, "Lockdown lifted")
+        .field("Unlocked channels", unlocked_channels.join(" "))
+        .post_from_context(ctx)
+        .await;
+
+    let mut reply = format!("Unlocked {}", unlocked_channels.join(" "));
+    if !failed_channels.is_empty() {
+        reply += &format!("\nFailed to unlock {}", failed_channels.join(" "));
+    }
+    ctx.say(reply).await?;
+
+    Ok(())
+}
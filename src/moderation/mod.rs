@@ -1,9 +1,29 @@
+mod cases;
+mod duration;
+mod lockdown;
+mod modlog;
+mod permissions;
+mod purge;
+mod report;
+mod scheduler;
 mod slowmode;
-pub use slowmode::slowmode;
+mod tempban;
+mod timeout;
+pub use cases::{cases, record_case, warn, CaseKind};
+pub use duration::Duration;
+pub use lockdown::{lockdown, unlock};
+pub use modlog::ModLogEntry;
+pub use permissions::permissions;
+pub use purge::purge;
+pub use report::{on_report_button, report, report_message};
+pub use scheduler::Scheduler;
+pub use slowmode::{apply_slowmode, lift_slowmodes_of_invocation, slowmode, MAX_SLOWMODE_RATE};
+pub use tempban::tempban;
+pub use timeout::{timeout, untimeout};
 
 use anyhow::Error;
 use poise::serenity_prelude::{
-    self as serenity, CreateAllowedMentions, CreateMessage, CreateThread, GetMessages,
+    self as serenity, CreateAllowedMentions, CreateMessage, GetMessages,
 };
 
 use crate::types::Context;
@@ -38,9 +58,15 @@ pub async fn cleanup(
             (msg.author.id == ctx.data().bot_user_id)
                 && (*ctx.created_at() - *msg.timestamp).num_hours() < 24
         })
-        .take(num_messages);
+        .take(num_messages)
+        .collect::<Vec<_>>();
 
-    ctx.channel_id().delete_messages(&ctx, messages_to_delete).await?;
+    ctx.channel_id().delete_messages(&ctx, &messages_to_delete).await?;
+
+    ModLogEntry::from_context(ctx, "Bot messages cleaned up")
+        .field("Deleted messages", messages_to_delete.len().to_string())
+        .post_from_context(ctx)
+        .await;
 
     crate::helpers::acknowledge_success(ctx, "cat_uwu", '👌').await
 }
@@ -59,61 +85,6 @@ async fn latest_message_link(ctx: Context<'_>) -> String {
     }
 }
 
-/// Discreetly reports a user for breaking the rules
-///
-/// Call this command in a channel when someone might be breaking the rules, for example by being \
-/// very rude, or starting discussions about divisive topics like politics and religion. Nobody \
-/// will see that you invoked this command.
-///
-/// Your report, along with a link to the \
-/// channel and its most recent message, will show up in a dedicated reports channel for \
-/// moderators, and it allows them to deal with it much faster than if you were to DM a \
-/// potentially AFK moderator.
-///
-/// You can still always ping the Moderator role if you're comfortable doing so.
-#[poise::command(slash_command, ephemeral, hide_in_help, category = "Moderation")]
-pub async fn report(
-    ctx: Context<'_>,
-    #[description = "What did the user do wrong?"] reason: String,
-) -> anyhow::Result<()> {
-    let reports_channel =
-        ctx.data().reports_channel.ok_or(anyhow::anyhow!("No reports channel was configured"))?;
-
-    let naughty_channel = ctx
-        .channel_id()
-        .to_channel(&ctx)
-        .await?
-        .guild()
-        .ok_or(anyhow::anyhow!("This command can only be used in a guild"))?;
-
-    let report_name = format!("Report {}", ctx.id() % 1000);
-
-    let builder = CreateThread::new(report_name).kind(serenity::ChannelType::PrivateThread);
-
-    // let msg = reports_channel.say(&ctx, &report_name).await?;
-    let report_thread = reports_channel.create_thread(&ctx, builder).await?;
-
-    let thread_message_content = format!(
-        "Hey <@&{}>, <@{}> sent a report from channel {}: {}\n> {}",
-        ctx.data().mod_role_id.get(),
-        ctx.author().id.get(),
-        naughty_channel.name,
-        latest_message_link(ctx).await,
-        reason
-    );
-
-    let allowed_mentions =
-        CreateAllowedMentions::new().users([ctx.author().id]).roles([ctx.data().mod_role_id]);
-    let builder =
-        CreateMessage::new().content(thread_message_content).allowed_mentions(allowed_mentions);
-    report_thread.send_message(&ctx, builder).await?;
-
-    ctx.say("Successfully sent report. Thanks for helping to make this community a better place!")
-        .await?;
-
-    Ok(())
-}
-
 /// Move a discussion to another channel
 ///
 /// Move a discussion to a specified channel, optionally pinging a list of users in the new channel.
@@ -131,7 +102,7 @@ pub async fn move_(
     }
 
     // DON'T use GuildChannel::permissions_for_user - it requires member to be cached
-    let guild = ctx.data().discord_guild_id.to_partial_guild(&ctx).await?;
+    let guild = target_channel.guild_id.to_partial_guild(&ctx).await?;
     let member = guild.member(&ctx, ctx.author().id).await?;
     let permissions_in_target_channel = guild.user_permissions_in(&target_channel, &member);
     if !permissions_in_target_channel.send_messages() {
@@ -160,10 +131,24 @@ pub async fn move_(
     }
 
     // let comefrom_message = target_channel.say(&ctx, comefrom_message).await?;
-    let allowed_mentions = CreateAllowedMentions::new().users(users_to_ping);
+    let allowed_mentions = CreateAllowedMentions::new().users(&users_to_ping);
     let builder = CreateMessage::new().content(comefrom_message).allowed_mentions(allowed_mentions);
     let comefrom_message = target_channel.send_message(&ctx, builder).await?;
 
+    let mut log_entry = ModLogEntry::from_context(ctx, "Discussion moved")
+        .field("Target channel", target_channel.mention().to_string());
+    if !users_to_ping.is_empty() {
+        log_entry = log_entry.field(
+            "Pinged users",
+            users_to_ping
+                .iter()
+                .map(|user| user.mention().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+    }
+    log_entry.post_from_context(ctx).await;
+
     ctx.say(format!(
         "**{} suggested to move this discussion to {}**\n{}",
         &ctx.author().tag(),
@@ -0,0 +1,80 @@
+use poise::serenity_prelude as serenity;
+
+use crate::types::{Context, Data};
+
+/// A moderation action to be posted as an embed in the mod log channel
+pub struct ModLogEntry {
+    embed: serenity::CreateEmbed,
+}
+
+impl ModLogEntry {
+    pub fn new(action: &str, actor: serenity::UserId) -> Self {
+        let embed = serenity::CreateEmbed::new()
+            .title(action)
+            .field("Moderator", format!("<@{}>", actor.get()), true)
+            .timestamp(serenity::Timestamp::now())
+            .color(crate::types::EMBED_COLOR);
+        Self { embed }
+    }
+
+    /// Fills in the invoking user as moderator and the channel the command was invoked in
+    pub fn from_context(ctx: Context<'_>, action: &str) -> Self {
+        Self::new(action, ctx.author().id).channel(ctx.channel_id())
+    }
+
+    pub fn target(self, user_id: serenity::UserId) -> Self {
+        self.field("User", format!("<@{}> ({})", user_id.get(), user_id.get()))
+    }
+
+    pub fn channel(self, channel_id: serenity::ChannelId) -> Self {
+        self.field("Channel", format!("<#{}>", channel_id.get()))
+    }
+
+    pub fn case(self, case_id: i64) -> Self {
+        self.field("Case", format!("#{}", case_id))
+    }
+
+    pub fn reason(self, reason: Option<&str>) -> Self {
+        self.field("Reason", reason.unwrap_or("_no reason given_"))
+    }
+
+    pub fn field(mut self, name: &str, value: impl AsRef<str>) -> Self {
+        self.embed =
+            self.embed.field(name, crate::helpers::truncate_for_field(value.as_ref()), true);
+        self
+    }
+
+    /// Posts the entry to the mod log channel, if one was configured. Failures are only logged,
+    /// so that they don't abort the moderation action itself
+    pub async fn post(
+        self,
+        http: impl serenity::CacheHttp,
+        mod_log_channel: Option<serenity::ChannelId>,
+    ) {
+        let Some(mod_log_channel) = mod_log_channel else {
+            return;
+        };
+
+        let builder = serenity::CreateMessage::new().embed(self.embed);
+        if let Err(e) = mod_log_channel.send_message(http, builder).await {
+            log::warn!("Failed to post in mod log channel: {}", e);
+        }
+    }
+
+    /// Posts the entry to the mod log channel of the guild the command was invoked in
+    pub async fn post_from_context(self, ctx: Context<'_>) {
+        let mod_log_channel = ctx.data().settings(ctx.guild_id()).mod_log_channel;
+        self.post(ctx, mod_log_channel).await;
+    }
+
+    /// Posts the entry to the mod log channel of the guild, for actions outside of commands
+    pub async fn post_in_guild(
+        self,
+        http: impl serenity::CacheHttp,
+        data: &Data,
+        guild_id: impl Into<Option<serenity::GuildId>>,
+    ) {
+        let mod_log_channel = data.settings(guild_id).mod_log_channel;
+        self.post(http, mod_log_channel).await;
+    }
+}
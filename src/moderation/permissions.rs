@@ -0,0 +1,218 @@
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+
+use super::ModLogEntry;
+use crate::checks::PermissionRule;
+use crate::types::{Context, Data};
+
+/// Normalizes a rule target and makes sure it refers to an existing command or category
+fn resolve_target(ctx: Context<'_>, target: &str) -> Result<String, Error> {
+    fn collect_targets(commands: &[poise::Command<Data, Error>], targets: &mut Vec<String>) {
+        for command in commands {
+            targets.push(command.qualified_name.to_lowercase());
+            if let Some(category) = &command.category {
+                targets.push(format!("category:{}", category.to_lowercase()));
+            }
+            collect_targets(&command.subcommands, targets);
+        }
+    }
+
+    let mut targets = Vec::new();
+    collect_targets(&ctx.framework().options().commands, &mut targets);
+
+    let target = target.trim().to_lowercase();
+    if !targets.contains(&target) {
+        anyhow::bail!(
+            "There is no command or category `{}`. Use the full command name like `filter add`, \
+             or a category like `category:moderation`",
+            target
+        );
+    }
+    let command = target.split(' ').next().unwrap_or(&target);
+    if crate::checks::ALWAYS_MODERATOR_ONLY.contains(&command) {
+        anyhow::bail!("`{}` is always only available to moderators", command);
+    }
+    Ok(target)
+}
+
+/// Parses permission names like `MANAGE_MESSAGES` or `manage messages`
+fn parse_permission(name: &str) -> Result<serenity::Permissions, Error> {
+    let name = name.trim().to_uppercase().replace(' ', "_");
+    serenity::Permissions::from_name(&name)
+        .ok_or(anyhow::anyhow!("Unknown permission `{}`, try something like MANAGE_MESSAGES", name))
+}
+
+fn describe_rule(rule: &PermissionRule) -> String {
+    let grantee = match (rule.role_id, rule.permissions) {
+        (Some(role_id), _) => format!("role <@&{}>", role_id),
+        (None, Some(permissions)) => {
+            let permissions = serenity::Permissions::from_bits_truncate(permissions as u64);
+            let names = permissions.iter_names().map(|(name, _)| name).collect::<Vec<_>>();
+            format!("permission `{}`", names.join(" + "))
+        },
+        (None, None) => "nobody".to_owned(),
+    };
+    format!("#{} `{}`: {}", rule.id, rule.target, grantee)
+}
+
+async fn add_rule(
+    ctx: Context<'_>,
+    target: &str,
+    role_id: Option<serenity::RoleId>,
+    permissions: Option<serenity::Permissions>,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+    let target = resolve_target(ctx, target)?;
+
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO command_permissions
+         (guild_id, target, role_id, permissions, created_by, created_at)
+         VALUES (?, ?, ?, ?, ?, ?)
+         RETURNING id",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(&target)
+    .bind(role_id.map(|role_id| role_id.get() as i64))
+    .bind(permissions.map(|permissions| permissions.bits() as i64))
+    .bind(ctx.author().id.get() as i64)
+    .bind(chrono::Utc::now().timestamp())
+    .fetch_one(&ctx.data().database)
+    .await?;
+    ctx.data().guild_settings.reload_permission_rules(&ctx.data().database).await?;
+
+    let description = describe_rule(&PermissionRule {
+        id,
+        target,
+        role_id: role_id.map(|role_id| role_id.get() as i64),
+        permissions: permissions.map(|permissions| permissions.bits() as i64),
+    });
+    ModLogEntry::from_context(ctx, "Permission rule added")
+        .field("Rule", &description)
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(format!("Added rule {}", description)).await?;
+
+    Ok(())
+}
+
+/// Configures who may use which commands (moderator only)
+///
+/// Without rules, moderation commands like `purge` are only available to moderators and all \
+/// other commands are open to everyone. Once a command, its parent command or its category has \
+/// rules, only members with one of the allowed roles or permissions may use it. The most \
+/// specific target with rules decides: rules for `filter add` take precedence over rules for \
+/// `filter`, which take precedence over rules for `category:moderation`. Moderators may always \
+/// use every command. Moderation commands are only opened by rules for the command itself, not \
+/// by rules for their category, and `permissions`, `raidmode` and `register` are always only \
+/// available to moderators.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    hide_in_help,
+    category = "Moderation",
+    subcommands(
+        "permissions_role",
+        "permissions_permission",
+        "permissions_remove",
+        "permissions_list"
+    ),
+    subcommand_required
+)]
+pub async fn permissions(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Allows a role to use a command or category
+///
+/// ?permissions role <command|category:name> @role
+#[poise::command(prefix_command, slash_command, guild_only, rename = "role")]
+pub async fn permissions_role(
+    ctx: Context<'_>,
+    #[description = "Command like `purge` or `filter add`, or category like `category:moderation`"]
+    target: String,
+    #[description = "Role to allow"] role: serenity::Role,
+) -> Result<(), Error> {
+    add_rule(ctx, &target, Some(role.id), None).await
+}
+
+/// Allows members with a Discord permission to use a command or category
+///
+/// ?permissions permission <command|category:name> <PERMISSION>
+#[poise::command(prefix_command, slash_command, guild_only, rename = "permission")]
+pub async fn permissions_permission(
+    ctx: Context<'_>,
+    #[description = "Command like `purge` or `filter add`, or category like `category:moderation`"]
+    target: String,
+    #[description = "Required permission, e.g. MANAGE_MESSAGES"]
+    #[rest]
+    permission: String,
+) -> Result<(), Error> {
+    let permission = parse_permission(&permission)?;
+    add_rule(ctx, &target, None, Some(permission)).await
+}
+
+/// Removes a permission rule
+///
+/// ?permissions remove <id>
+#[poise::command(prefix_command, slash_command, guild_only, rename = "remove")]
+pub async fn permissions_remove(
+    ctx: Context<'_>,
+    #[description = "ID of the rule, as shown by `?permissions list`"] rule_id: i64,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    let rule: Option<PermissionRule> = sqlx::query_as(
+        "DELETE FROM command_permissions WHERE id = ? AND guild_id = ?
+         RETURNING id, target, role_id, permissions",
+    )
+    .bind(rule_id)
+    .bind(guild_id.get() as i64)
+    .fetch_optional(&ctx.data().database)
+    .await?;
+    let rule = rule.ok_or(anyhow::anyhow!("There is no rule #{}", rule_id))?;
+    ctx.data().guild_settings.reload_permission_rules(&ctx.data().database).await?;
+
+    ModLogEntry::from_context(ctx, "Permission rule removed")
+        .field("Rule", describe_rule(&rule))
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(format!("Removed rule {}", describe_rule(&rule))).await?;
+
+    Ok(())
+}
+
+/// Lists all permission rules
+#[poise::command(prefix_command, slash_command, guild_only, rename = "list")]
+pub async fn permissions_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    let rules: Vec<PermissionRule> = sqlx::query_as(
+        "SELECT id, target, role_id, permissions FROM command_permissions
+         WHERE guild_id = ? ORDER BY target, id",
+    )
+    .bind(guild_id.get() as i64)
+    .fetch_all(&ctx.data().database)
+    .await?;
+
+    if rules.is_empty() {
+        ctx.say("No permission rules configured, all commands are open to everyone").await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for rule in &rules {
+        list += &describe_rule(rule);
+        list += "\n";
+    }
+
+    crate::helpers::reply_potentially_long_text(ctx, &list, "", async {
+        "\n(list was truncated)".into()
+    })
+    .await
+}
@@ -0,0 +1,271 @@
+use std::str::FromStr;
+
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+
+use super::ModLogEntry;
+use crate::types::Context;
+
+/// Upper bound for the count argument
+const MAX_PURGE_COUNT: usize = 1000;
+/// How far back the channel history is searched for matching messages
+const MAX_SCANNED_MESSAGES: usize = 5000;
+/// Discord refuses to bulk delete messages older than two weeks. An hour of margin avoids
+/// failures for messages that cross the limit while the purge is running
+const BULK_DELETE_MAX_AGE: chrono::Duration = chrono::Duration::hours(14 * 24 - 1);
+
+/// Which messages `?purge` deletes, e.g. `user=@x contains="some text" bots attachments`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PurgeFilters {
+    user: Option<serenity::UserId>,
+    /// Lowercased
+    contains: Option<String>,
+    bots: bool,
+    attachments: bool,
+}
+
+#[derive(Debug)]
+pub struct PurgeFiltersParseError(String);
+
+impl std::fmt::Display for PurgeFiltersParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid filter `{}`, expected `user=@user`, `contains=text`, `bots` or `attachments`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for PurgeFiltersParseError {}
+
+/// Splits at whitespace, except inside double quotes, which are removed
+fn split_arguments(s: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in s.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    arguments.push(std::mem::take(&mut current));
+                }
+            },
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        arguments.push(current);
+    }
+    arguments
+}
+
+impl FromStr for PurgeFilters {
+    type Err = PurgeFiltersParseError;
+
+    fn from_str(s: &str) -> Result<Self, PurgeFiltersParseError> {
+        let mut filters = Self::default();
+        for argument in split_arguments(s) {
+            let invalid = || PurgeFiltersParseError(argument.clone());
+            match argument.split_once('=') {
+                Some(("user", user)) => {
+                    let user_id = user
+                        .trim_start_matches("<@")
+                        .trim_start_matches('!')
+                        .trim_end_matches('>')
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|&user_id| user_id != 0)
+                        .ok_or_else(invalid)?;
+                    filters.user = Some(serenity::UserId::new(user_id));
+                },
+                Some(("contains", text)) if !text.is_empty() => {
+                    filters.contains = Some(text.to_lowercase());
+                },
+                None if argument == "bots" => filters.bots = true,
+                None if argument == "attachments" => filters.attachments = true,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(filters)
+    }
+}
+
+impl PurgeFilters {
+    fn matches(&self, message: &serenity::Message) -> bool {
+        self.user.is_none_or(|user_id| message.author.id == user_id)
+            && self
+                .contains
+                .as_ref()
+                .is_none_or(|text| message.content.to_lowercase().contains(text))
+            && (!self.bots || message.author.bot)
+            && (!self.attachments || !message.attachments.is_empty())
+    }
+}
+
+impl std::fmt::Display for PurgeFilters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(user_id) = self.user {
+            parts.push(format!("from <@{}>", user_id));
+        }
+        if let Some(text) = &self.contains {
+            parts.push(format!("containing `{}`", text));
+        }
+        if self.bots {
+            parts.push("from bots".to_owned());
+        }
+        if self.attachments {
+            parts.push("with attachments".to_owned());
+        }
+
+        if parts.is_empty() {
+            f.write_str("all messages")
+        } else {
+            f.write_str(&parts.join(", "))
+        }
+    }
+}
+
+/// Pages backwards through the channel history, starting before the invocation, until enough
+/// matching messages are found or the scan limit is reached
+async fn find_messages(
+    ctx: Context<'_>,
+    count: usize,
+    filters: &PurgeFilters,
+) -> Result<Vec<serenity::Message>, Error> {
+    let mut matching_messages = Vec::new();
+    let mut before = serenity::MessageId::new(ctx.id());
+    let mut scanned_messages = 0;
+
+    while matching_messages.len() < count && scanned_messages < MAX_SCANNED_MESSAGES {
+        let page = ctx
+            .channel_id()
+            .messages(ctx, serenity::GetMessages::new().before(before).limit(100))
+            .await?;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+        before = oldest.id;
+        scanned_messages += page.len();
+
+        matching_messages.extend(page.into_iter().filter(|message| filters.matches(message)));
+    }
+
+    matching_messages.truncate(count);
+    Ok(matching_messages)
+}
+
+/// Deletes the messages in bulk where possible, and one by one where they're too old for bulk
+/// deletion. Returns how many messages were deleted
+async fn delete_messages(ctx: Context<'_>, messages: Vec<serenity::Message>) -> usize {
+    let now = chrono::Utc::now();
+    let (recent_messages, old_messages): (Vec<_>, Vec<_>) = messages
+        .into_iter()
+        .map(|message| (message.id, *message.timestamp))
+        .partition(|&(_, timestamp)| now - timestamp < BULK_DELETE_MAX_AGE);
+
+    let mut deleted = 0;
+    let recent_message_ids = recent_messages.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+    for chunk in recent_message_ids.chunks(100) {
+        // Bulk deletion requires at least two messages
+        let result = match chunk {
+            [message_id] => ctx.channel_id().delete_message(ctx, message_id).await,
+            chunk => ctx.channel_id().delete_messages(ctx, chunk).await,
+        };
+        match result {
+            Ok(()) => deleted += chunk.len(),
+            Err(e) => log::warn!("Failed to bulk delete messages: {}", e),
+        }
+    }
+
+    for (message_id, _) in old_messages {
+        match ctx.channel_id().delete_message(ctx, message_id).await {
+            Ok(()) => deleted += 1,
+            Err(e) => log::warn!("Failed to delete message {}: {}", message_id, e),
+        }
+    }
+
+    deleted
+}
+
+/// Deletes recent messages in this channel, optionally filtered (moderator only)
+///
+/// ?purge <count> [user=@user] [contains=text] [bots] [attachments]
+///
+/// Searches the channel history for the `count` most recent messages matching all given \
+/// filters and deletes them. Use quotes for text with spaces, e.g. `contains="free nitro"`. \
+/// Messages older than two weeks can't be bulk deleted, so they are deleted one by one, which \
+/// is slow.
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Moderation")]
+pub async fn purge(
+    ctx: Context<'_>,
+    #[description = "How many messages to delete"]
+    #[min = 1]
+    #[max = 1000]
+    count: usize,
+    #[description = "Filters, e.g. user=@user contains=\"some text\" bots attachments"]
+    #[rest]
+    filters: Option<PurgeFilters>,
+) -> Result<(), Error> {
+    let filters = filters.unwrap_or_default();
+    let count = count.min(MAX_PURGE_COUNT);
+    ctx.defer_ephemeral().await?;
+
+    let messages = find_messages(ctx, count, &filters).await?;
+    let deleted = delete_messages(ctx, messages).await;
+
+    ModLogEntry::from_context(ctx, "Messages purged")
+        .field("Deleted", deleted.to_string())
+        .field("Filters", filters.to_string())
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say(format!("Deleted {} messages ({})", deleted, filters)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_at_whitespace_outside_quotes() {
+        assert_eq!(split_arguments("  bots   attachments "), ["bots", "attachments"]);
+        assert_eq!(split_arguments(r#"contains="some  text" bots"#), [
+            "contains=some  text",
+            "bots"
+        ]);
+        assert_eq!(split_arguments(r#""contains=a b""#), ["contains=a b"]);
+        assert_eq!(split_arguments(r#"contains="unterminated quote"#), [
+            "contains=unterminated quote"
+        ]);
+        assert!(split_arguments("").is_empty());
+        assert!(split_arguments(r#""""#).is_empty());
+    }
+
+    #[test]
+    fn parses_filters() {
+        let filters: PurgeFilters =
+            r#"user=<@!1234> contains="Some Text" bots attachments"#.parse().unwrap();
+        assert_eq!(filters, PurgeFilters {
+            user: Some(serenity::UserId::new(1234)),
+            contains: Some("some text".to_owned()),
+            bots: true,
+            attachments: true,
+        });
+
+        assert_eq!("user=<@1234>".parse::<PurgeFilters>().unwrap().user.unwrap().get(), 1234);
+        assert_eq!("user=1234".parse::<PurgeFilters>().unwrap().user.unwrap().get(), 1234);
+        assert_eq!("".parse::<PurgeFilters>().unwrap(), PurgeFilters::default());
+    }
+
+    #[test]
+    fn rejects_invalid_filters() {
+        for invalid in ["user=", "user=0", "user=@someone", "contains=", "bot", "bots=yes", "x=y"] {
+            assert!(invalid.parse::<PurgeFilters>().is_err(), "`{}` should be rejected", invalid);
+        }
+    }
+}
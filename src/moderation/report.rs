@@ -0,0 +1,430 @@
+use anyhow::Error;
+use poise::serenity_prelude::{
+    self as serenity, CreateAllowedMentions, CreateMessage, CreateThread,
+};
+use poise::Modal as _;
+
+use super::ModLogEntry;
+use crate::types::{ApplicationContext, Context, Data};
+
+/// How long a moderator has to fill in the modal when closing a report
+const CLOSE_MODAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+/// How long a member has to fill in the reason when reporting a message
+const REPORT_MODAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+enum ReportStatus {
+    Open,
+    Claimed,
+    Resolved,
+    Dismissed,
+}
+
+impl ReportStatus {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Claimed => "claimed",
+            Self::Resolved => "resolved",
+            Self::Dismissed => "dismissed",
+        }
+    }
+}
+
+/// The buttons below a report. Their custom IDs contain the report ID, so they keep working after
+/// the bot restarts
+fn report_buttons(report_id: i64) -> Vec<serenity::CreateActionRow> {
+    let button = |action: &str, label: &str, style| {
+        serenity::CreateButton::new(format!("report:{}:{}", action, report_id))
+            .label(label)
+            .style(style)
+    };
+    vec![serenity::CreateActionRow::Buttons(vec![
+        button("claim", "Claim", serenity::ButtonStyle::Primary),
+        button("resolve", "Resolve", serenity::ButtonStyle::Success),
+        button("dismiss", "Dismiss", serenity::ButtonStyle::Secondary),
+    ])]
+}
+
+/// Opens a private thread in the reports channel and posts the report in it, pinging the
+/// moderators and the reporter. Returns the report ID
+async fn create_report_thread(ctx: Context<'_>, report: CreateMessage) -> Result<i64, Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command can only be used in a guild"))?;
+    let settings = ctx.data().settings(guild_id);
+    let reports_channel =
+        settings.reports_channel.ok_or(anyhow::anyhow!("No reports channel was configured"))?;
+
+    let report_id: i64 = sqlx::query_scalar(
+        "INSERT INTO reports (guild_id, reporter_id, status, created_at) VALUES (?, ?, ?, ?)
+         RETURNING id",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(ctx.author().id.get() as i64)
+    .bind(ReportStatus::Open)
+    .bind(ctx.created_at().timestamp())
+    .fetch_one(&ctx.data().database)
+    .await?;
+
+    let builder = CreateThread::new(format!("Report #{}", report_id))
+        .kind(serenity::ChannelType::PrivateThread);
+    let report_thread = reports_channel.create_thread(&ctx, builder).await?;
+
+    sqlx::query("UPDATE reports SET thread_id = ? WHERE id = ?")
+        .bind(report_thread.id.get() as i64)
+        .bind(report_id)
+        .execute(&ctx.data().database)
+        .await?;
+
+    let allowed_mentions =
+        CreateAllowedMentions::new().users([ctx.author().id]).roles(settings.mod_role_id);
+    let report = report.allowed_mentions(allowed_mentions).components(report_buttons(report_id));
+    report_thread.send_message(&ctx, report).await?;
+
+    Ok(report_id)
+}
+
+/// Discreetly reports a user for breaking the rules
+///
+/// Call this command in a channel when someone might be breaking the rules, for example by being \
+/// very rude, or starting discussions about divisive topics like politics and religion. Nobody \
+/// will see that you invoked this command.
+///
+/// Your report, along with a link to the \
+/// channel and its most recent message, will show up in a dedicated reports channel for \
+/// moderators, and it allows them to deal with it much faster than if you were to DM a \
+/// potentially AFK moderator.
+///
+/// You can still always ping the Moderator role if you're comfortable doing so. To report a \
+/// specific message, right-click it and select Apps > Report message.
+#[poise::command(slash_command, ephemeral, hide_in_help, category = "Moderation")]
+pub async fn report(
+    ctx: Context<'_>,
+    #[description = "What did the user do wrong?"] reason: String,
+) -> anyhow::Result<()> {
+    let naughty_channel = ctx
+        .channel_id()
+        .to_channel(&ctx)
+        .await?
+        .guild()
+        .ok_or(anyhow::anyhow!("This command can only be used in a guild"))?;
+
+    let thread_message_content = format!(
+        "Hey {}, <@{}> sent a report from channel {}: {}\n> {}",
+        ctx.data().settings(ctx.guild_id()).mod_role_mention(),
+        ctx.author().id.get(),
+        naughty_channel.name,
+        super::latest_message_link(ctx).await,
+        reason
+    );
+    let report_id =
+        create_report_thread(ctx, CreateMessage::new().content(thread_message_content)).await?;
+
+    ctx.say(format!(
+        "Successfully sent report #{}. Thanks for helping to make this community a better place!",
+        report_id
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, poise::Modal)]
+#[name = "Report message"]
+struct ReportModal {
+    #[name = "What did the user do wrong?"]
+    #[paragraph]
+    #[max_length = 1000]
+    reason: String,
+}
+
+/// Copies the message into the report, so that the evidence survives if the message is deleted
+async fn snapshot_message(
+    guild_id: serenity::GuildId,
+    message: &serenity::Message,
+) -> (serenity::CreateEmbed, Vec<serenity::CreateAttachment>) {
+    let content = if message.content.is_empty() { "_<no text>_" } else { &message.content };
+    let mut embed = serenity::CreateEmbed::new()
+        .author(
+            serenity::CreateEmbedAuthor::new(message.author.tag()).icon_url(message.author.face()),
+        )
+        .description(content)
+        .field("Author", format!("<@{0}> ({0})", message.author.id), true)
+        .field("Channel", format!("<#{}>", message.channel_id), true)
+        .field(
+            "Message",
+            format!(
+                "https://discord.com/channels/{}/{}/{}",
+                guild_id, message.channel_id, message.id
+            ),
+            true,
+        )
+        .timestamp(message.timestamp)
+        .color(crate::types::EMBED_COLOR);
+
+    let (files, attachment_list) = crate::helpers::copy_attachments(&message.attachments).await;
+    if !attachment_list.is_empty() {
+        embed = embed.field(
+            "Attachments",
+            crate::helpers::truncate_for_field(&attachment_list.join("\n")),
+            false,
+        );
+    }
+
+    (embed, files)
+}
+
+/// Reports this exact message to the moderators
+///
+/// The message's content, author and attachments are copied into the report, so the moderators \
+/// can still see them if the message gets deleted. Nobody will see that you reported it.
+#[poise::command(
+    context_menu_command = "Report message",
+    ephemeral,
+    guild_only,
+    hide_in_help,
+    category = "Moderation"
+)]
+pub async fn report_message(
+    ctx: ApplicationContext<'_>,
+    #[description = "Message to report"] message: serenity::Message,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    // A modal must be the first response to the interaction
+    let modal =
+        poise::execute_modal::<Data, Error, ReportModal>(ctx, None, Some(REPORT_MODAL_TIMEOUT))
+            .await?;
+    let Some(ReportModal { reason }) = modal else {
+        return Ok(());
+    };
+
+    let (embed, files) = snapshot_message(guild_id, &message).await;
+    let content = format!(
+        "Hey {}, <@{}> reported a message by <@{}>:\n>>> {}",
+        ctx.data().settings(guild_id).mod_role_mention(),
+        ctx.author().id.get(),
+        message.author.id.get(),
+        reason
+    );
+    let report = CreateMessage::new().content(content).embed(embed).add_files(files);
+    let report_id = create_report_thread(Context::Application(ctx), report).await?;
+
+    ctx.say(format!(
+        "Successfully sent report #{}. Thanks for helping to make this community a better place!",
+        report_id
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, poise::Modal)]
+#[name = "Close report"]
+struct CloseReportModal {
+    #[name = "Message to the reporter"]
+    #[placeholder = "Leave empty to close the report without notifying the reporter"]
+    #[paragraph]
+    #[max_length = 1000]
+    message_to_reporter: Option<String>,
+}
+
+async fn respond_ephemeral(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+    content: impl Into<String>,
+) -> Result<(), Error> {
+    let message =
+        serenity::CreateInteractionResponseMessage::new().content(content).ephemeral(true);
+    interaction.create_response(ctx, serenity::CreateInteractionResponse::Message(message)).await?;
+    Ok(())
+}
+
+/// Explains why a report can't be claimed or closed anymore
+async fn describe_handled_report(data: &Data, report_id: i64) -> Result<String, Error> {
+    let (status, handled_by): (ReportStatus, Option<i64>) =
+        sqlx::query_as("SELECT status, handled_by FROM reports WHERE id = ?")
+            .bind(report_id)
+            .fetch_one(&data.database)
+            .await?;
+
+    Ok(match handled_by {
+        Some(handled_by) => {
+            format!("This report was already {} by <@{}>", status.label(), handled_by)
+        },
+        None => format!("This report is already {}", status.label()),
+    })
+}
+
+/// Handles the Claim, Resolve and Dismiss buttons below reports. Only moderators may press them
+pub async fn on_report_button(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::ComponentInteraction,
+) -> Result<(), Error> {
+    let Some(button) = interaction.data.custom_id.strip_prefix("report:") else {
+        return Ok(());
+    };
+    let Some((action, report_id)) = button.split_once(':') else {
+        return Ok(());
+    };
+    let report_id: i64 = report_id.parse()?;
+
+    let is_moderator = interaction
+        .member
+        .as_ref()
+        .is_some_and(|member| data.settings(interaction.guild_id).is_moderator(&member.roles));
+    if !is_moderator {
+        return respond_ephemeral(ctx, interaction, "Only moderators can handle reports").await;
+    }
+
+    match action {
+        "claim" => claim_report(ctx, data, interaction, report_id).await,
+        "resolve" => close_report(ctx, data, interaction, report_id, ReportStatus::Resolved).await,
+        "dismiss" => close_report(ctx, data, interaction, report_id, ReportStatus::Dismissed).await,
+        _ => Ok(()),
+    }
+}
+
+async fn claim_report(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::ComponentInteraction,
+    report_id: i64,
+) -> Result<(), Error> {
+    let moderator = interaction.user.id;
+    let claimed =
+        sqlx::query("UPDATE reports SET status = ?, handled_by = ? WHERE id = ? AND status = ?")
+            .bind(ReportStatus::Claimed)
+            .bind(moderator.get() as i64)
+            .bind(report_id)
+            .bind(ReportStatus::Open)
+            .execute(&data.database)
+            .await?
+            .rows_affected()
+            > 0;
+    if !claimed {
+        let description = describe_handled_report(data, report_id).await?;
+        return respond_ephemeral(ctx, interaction, description).await;
+    }
+
+    let message = serenity::CreateInteractionResponseMessage::new()
+        .content(format!("<@{}> claimed this report", moderator))
+        .allowed_mentions(CreateAllowedMentions::new());
+    interaction.create_response(ctx, serenity::CreateInteractionResponse::Message(message)).await?;
+
+    ModLogEntry::new("Report claimed", moderator)
+        .field("Report", format!("#{}", report_id))
+        .channel(interaction.channel_id)
+        .post_in_guild(ctx, data, interaction.guild_id)
+        .await;
+
+    Ok(())
+}
+
+/// Asks the moderator for an optional message to the reporter, then closes the report, removes
+/// the buttons and archives the thread
+async fn close_report(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::ComponentInteraction,
+    report_id: i64,
+    status: ReportStatus,
+) -> Result<(), Error> {
+    let current_status: ReportStatus =
+        sqlx::query_scalar("SELECT status FROM reports WHERE id = ?")
+            .bind(report_id)
+            .fetch_one(&data.database)
+            .await?;
+    if !matches!(current_status, ReportStatus::Open | ReportStatus::Claimed) {
+        let description = describe_handled_report(data, report_id).await?;
+        return respond_ephemeral(ctx, interaction, description).await;
+    }
+
+    let modal_custom_id = interaction.id.to_string();
+    interaction
+        .create_response(ctx, CloseReportModal::create(None, modal_custom_id.clone()))
+        .await?;
+    let submission = serenity::ModalInteractionCollector::new(&ctx.shard)
+        .filter(move |submission| submission.data.custom_id == modal_custom_id)
+        .timeout(CLOSE_MODAL_TIMEOUT)
+        .await;
+    let Some(submission) = submission else {
+        return Ok(());
+    };
+    let CloseReportModal { message_to_reporter } =
+        CloseReportModal::parse(submission.data.clone()).map_err(anyhow::Error::msg)?;
+
+    // Another moderator may have closed the report while the modal was open
+    let moderator = interaction.user.id;
+    let report: Option<(i64,)> = sqlx::query_as(
+        "UPDATE reports SET status = ?, handled_by = ?, closed_at = ?
+         WHERE id = ? AND status IN (?, ?)
+         RETURNING reporter_id",
+    )
+    .bind(status)
+    .bind(moderator.get() as i64)
+    .bind(chrono::Utc::now().timestamp())
+    .bind(report_id)
+    .bind(ReportStatus::Open)
+    .bind(ReportStatus::Claimed)
+    .fetch_optional(&data.database)
+    .await?;
+    let Some((reporter_id,)) = report else {
+        let description = describe_handled_report(data, report_id).await?;
+        let message =
+            serenity::CreateInteractionResponseMessage::new().content(description).ephemeral(true);
+        submission
+            .create_response(ctx, serenity::CreateInteractionResponse::Message(message))
+            .await?;
+        return Ok(());
+    };
+    let reporter_id = serenity::UserId::new(reporter_id as u64);
+
+    // The modal was opened from the report message, so the response can edit it
+    let without_buttons = serenity::CreateInteractionResponseMessage::new().components(vec![]);
+    submission
+        .create_response(ctx, serenity::CreateInteractionResponse::UpdateMessage(without_buttons))
+        .await?;
+
+    let mut notified_reporter = false;
+    if let Some(message_to_reporter) = &message_to_reporter {
+        let dm = CreateMessage::new().content(format!(
+            "Your report #{} has been {} by the moderators:\n>>> {}",
+            report_id,
+            status.label(),
+            message_to_reporter
+        ));
+        match reporter_id.direct_message(ctx, dm).await {
+            Ok(_) => notified_reporter = true,
+            Err(e) => log::info!("Couldn't notify reporter {}: {}", reporter_id, e),
+        }
+    }
+
+    let mut closing_message = format!("Report {} by <@{}>", status.label(), moderator);
+    if notified_reporter {
+        closing_message += ", the reporter was notified";
+    }
+    let builder = CreateMessage::new()
+        .content(closing_message)
+        .allowed_mentions(CreateAllowedMentions::new());
+    interaction.channel_id.send_message(ctx, builder).await?;
+
+    let builder = serenity::EditThread::new().archived(true).locked(true);
+    interaction.channel_id.edit_thread(ctx, builder).await?;
+
+    let action = match status {
+        ReportStatus::Dismissed => "Report dismissed",
+        _ => "Report resolved",
+    };
+    ModLogEntry::new(action, moderator)
+        .field("Report", format!("#{}", report_id))
+        .channel(interaction.channel_id)
+        .field("Message to reporter", message_to_reporter.as_deref().unwrap_or("_none_"))
+        .post_in_guild(ctx, data, interaction.guild_id)
+        .await;
+
+    Ok(())
+}
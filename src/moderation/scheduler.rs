@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use poise::serenity_prelude as serenity;
+
+/// How often the database is checked for moderation actions that have run out
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Background task that carries out time-delayed moderation actions, like lifting temporary bans,
+/// slowmodes or lockdowns.
+///
+/// Pending actions are stored in the database instead of being awaited inside the command, so
+/// they survive restarts of the bot. Actions that ran out while the bot was offline are carried
+/// out right after startup.
+pub struct Scheduler {
+    pub http: Arc<serenity::Http>,
+    pub database: sqlx::SqlitePool,
+    pub bot_user_id: serenity::UserId,
+    pub config: crate::config::SharedConfig,
+    pub guild_settings: crate::guild_settings::GuildSettingsStore,
+}
+
+impl Scheduler {
+    pub(super) fn mod_log_channel(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Option<serenity::ChannelId> {
+        self.guild_settings.resolve(&self.config.get(), Some(guild_id)).mod_log_channel
+    }
+
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = super::tempban::lift_expired_tempbans(&self).await {
+                log::warn!("Failed to lift expired temporary bans: {:?}", e);
+            }
+            if let Err(e) = super::slowmode::restore_expired_slowmodes(&self).await {
+                log::warn!("Failed to restore expired slowmodes: {:?}", e);
+            }
+            if let Err(e) = super::lockdown::lift_expired_lockdowns(&self).await {
+                log::warn!("Failed to lift expired lockdowns: {:?}", e);
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
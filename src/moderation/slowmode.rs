@@ -1,17 +1,30 @@
 use anyhow::Error;
-use poise::serenity_prelude::EditChannel;
+use poise::serenity_prelude::{self as serenity, EditChannel};
 
-use crate::types::Context;
+use super::ModLogEntry;
+use crate::types::{ActiveSlowmode, Context};
+
+/// The highest slowmode rate Discord allows, in seconds
+pub const MAX_SLOWMODE_RATE: u16 = 6 * 60 * 60;
 
 async fn immediately_lift_slowmode(ctx: Context<'_>) -> anyhow::Result<()> {
-    let active_slowmode = ctx.data().active_slowmodes.lock().unwrap().remove(&ctx.channel_id());
+    let previous_slowmode_rate: Option<u16> = sqlx::query_scalar(
+        "DELETE FROM active_slowmodes WHERE channel_id = ? RETURNING previous_slowmode_rate",
+    )
+    .bind(ctx.channel_id().get() as i64)
+    .fetch_optional(&ctx.data().database)
+    .await?;
 
-    match active_slowmode {
-        Some(active_slowmode) => {
-            let builder = EditChannel::new()
-                .rate_limit_per_user(active_slowmode.previous_slowmode_rate.try_into().unwrap());
+    match previous_slowmode_rate {
+        Some(previous_slowmode_rate) => {
+            let builder = EditChannel::new().rate_limit_per_user(previous_slowmode_rate);
             ctx.channel_id().edit(&ctx, builder).await?;
             ctx.say("Restored slowmode to previous level").await?;
+
+            ModLogEntry::from_context(ctx, "Slowmode lifted")
+                .field("Restored rate", format!("{} seconds", previous_slowmode_rate))
+                .post_from_context(ctx)
+                .await;
         },
         None => {
             ctx.say("There is no slowmode command currently running").await?;
@@ -24,8 +37,8 @@ async fn immediately_lift_slowmode(ctx: Context<'_>) -> anyhow::Result<()> {
 async fn register_slowmode(
     ctx: Context<'_>,
     duration_argument: Option<u64>,
-    rate_argument: Option<u64>,
-) -> Result<(u64, u64), Error> {
+    rate_argument: Option<u16>,
+) -> Result<(u32, u16), Error> {
     let current_slowmode_rate = match ctx.channel_id().to_channel(&ctx).await {
         Ok(channel) => channel
             .guild()
@@ -38,55 +51,172 @@ async fn register_slowmode(
         },
     };
 
-    let mut active_slowmodes = ctx.data().active_slowmodes.lock().unwrap();
-    let already_active_slowmode = active_slowmodes.get(&ctx.channel_id());
+    let already_active_slowmode: Option<ActiveSlowmode> =
+        sqlx::query_as("SELECT * FROM active_slowmodes WHERE channel_id = ?")
+            .bind(ctx.channel_id().get() as i64)
+            .fetch_optional(&ctx.data().database)
+            .await?;
+    let already_active_slowmode = already_active_slowmode.as_ref();
 
-    // If we're overwriting an existing slowmode command, the channel's current slowmode rate
-    // is not the original one, so we check the existing entry
-    let previous_slowmode_rate = already_active_slowmode
-        .map_or(current_slowmode_rate, |s| s.previous_slowmode_rate.try_into().unwrap());
-    let duration =
-        duration_argument.or_else(|| Some(already_active_slowmode?.duration)).unwrap_or(30);
-    let rate = rate_argument.or_else(|| Some(already_active_slowmode?.rate)).unwrap_or(15);
-
-    active_slowmodes.insert(ctx.channel_id(), crate::types::ActiveSlowmode {
-        previous_slowmode_rate: previous_slowmode_rate.into(),
+    let duration = match duration_argument {
+        Some(duration) => duration.try_into()?,
+        None => already_active_slowmode.map_or(30, |s| s.duration),
+    };
+    let rate = match rate_argument {
+        Some(rate) => rate,
+        None => already_active_slowmode.map_or(15, |s| s.rate),
+    };
+
+    upsert_active_slowmode(
+        &ctx.data().database,
+        ctx.channel_id(),
+        current_slowmode_rate,
         duration,
         rate,
-        invocation_time: *ctx.created_at(),
-    });
+        ctx.created_at().timestamp_millis(),
+    )
+    .await?;
 
     Ok((duration, rate))
 }
 
-async fn restore_slowmode_rate(ctx: Context<'_>) -> Result<(), Error> {
-    let previous_slowmode_rate = {
-        let mut active_slowmodes = ctx.data().active_slowmodes.lock().unwrap();
-        let active_slowmode = match active_slowmodes.remove(&ctx.channel_id()) {
-            Some(x) => x,
-            None => {
-                log::info!(
-                    "Slowmode entry has expired; this slowmode invocation has been overwritten"
-                );
-                return Ok(());
-            },
-        };
-        if active_slowmode.invocation_time != *ctx.created_at() {
-            log::info!(
-                "Slowmode entry has a different invocation time; this slowmode invocation has \
-                 been overwritten"
-            );
-            return Ok(());
+/// Registers a slowmode invocation. `invocation_time` is a unix timestamp in milliseconds
+async fn upsert_active_slowmode(
+    database: &sqlx::SqlitePool,
+    channel_id: serenity::ChannelId,
+    current_slowmode_rate: u16,
+    duration: u32,
+    rate: u16,
+    invocation_time: i64,
+) -> Result<(), Error> {
+    // If we're overwriting an existing slowmode command, the channel's current slowmode rate
+    // is not the original one, so the existing entry's previous rate is kept
+    sqlx::query(
+        "INSERT INTO active_slowmodes
+         (channel_id, previous_slowmode_rate, duration, rate, invocation_time, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT (channel_id) DO UPDATE SET
+         duration = excluded.duration,
+         rate = excluded.rate,
+         invocation_time = excluded.invocation_time,
+         expires_at = excluded.expires_at",
+    )
+    .bind(channel_id.get() as i64)
+    .bind(current_slowmode_rate)
+    .bind(duration)
+    .bind(rate)
+    .bind(invocation_time)
+    .bind(invocation_time / 1000 + 60 * i64::from(duration))
+    .execute(database)
+    .await?;
+
+    Ok(())
+}
+
+/// Applies a slowmode on behalf of the bot instead of a `?slowmode` invocation. It's lifted by the
+/// scheduler like any other slowmode, or earlier with [`lift_slowmodes_of_invocation`]
+pub async fn apply_slowmode(
+    http: impl serenity::CacheHttp,
+    database: &sqlx::SqlitePool,
+    channel: &serenity::GuildChannel,
+    duration: u32,
+    rate: u16,
+    invocation_time: i64,
+) -> Result<(), Error> {
+    let current_slowmode_rate = channel.rate_limit_per_user.unwrap_or(0);
+    upsert_active_slowmode(
+        database,
+        channel.id,
+        current_slowmode_rate,
+        duration,
+        rate,
+        invocation_time,
+    )
+    .await?;
+
+    let builder = EditChannel::new().rate_limit_per_user(rate);
+    channel.id.edit(http, builder).await?;
+
+    Ok(())
+}
+
+/// Immediately restores the previous rate of all channels slowed down by the given invocation, as
+/// long as they haven't been overwritten since. Returns the restored channels
+pub async fn lift_slowmodes_of_invocation(
+    http: impl serenity::CacheHttp,
+    database: &sqlx::SqlitePool,
+    invocation_time: i64,
+) -> Result<Vec<serenity::ChannelId>, Error> {
+    let lifted_slowmodes: Vec<(i64, u16)> = sqlx::query_as(
+        "DELETE FROM active_slowmodes WHERE invocation_time = ?
+         RETURNING channel_id, previous_slowmode_rate",
+    )
+    .bind(invocation_time)
+    .fetch_all(database)
+    .await?;
+
+    let mut restored_channels = Vec::new();
+    for (channel_id, previous_slowmode_rate) in lifted_slowmodes {
+        let channel_id = serenity::ChannelId::new(channel_id as u64);
+        let builder = EditChannel::new().rate_limit_per_user(previous_slowmode_rate);
+        match channel_id.edit(&http, builder).await {
+            Ok(_) => restored_channels.push(channel_id),
+            Err(e) => log::warn!("Failed to restore slowmode rate of {}: {}", channel_id, e),
         }
-        active_slowmode.previous_slowmode_rate
-    };
+    }
+
+    Ok(restored_channels)
+}
 
-    log::info!("Restoring slowmode rate to {}", previous_slowmode_rate);
+/// Restores the previous slowmode rate of all slowmodes which have run out, including those that
+/// ran out while the bot was offline
+pub(super) async fn restore_expired_slowmodes(scheduler: &super::Scheduler) -> Result<(), Error> {
+    let expired_slowmodes: Vec<ActiveSlowmode> =
+        sqlx::query_as("SELECT * FROM active_slowmodes WHERE expires_at <= ?")
+            .bind(chrono::Utc::now().timestamp())
+            .fetch_all(&scheduler.database)
+            .await?;
 
-    let builder =
-        EditChannel::new().rate_limit_per_user(previous_slowmode_rate.try_into().unwrap());
-    ctx.channel_id().edit(&ctx, builder).await?;
-    ctx.data().active_slowmodes.lock().unwrap().remove(&ctx.channel_id());
+    for active_slowmode in expired_slowmodes {
+        let channel_id = serenity::ChannelId::new(active_slowmode.channel_id as u64);
+        log::info!(
+            "Restoring slowmode rate of {} to {}",
+            channel_id,
+            active_slowmode.previous_slowmode_rate
+        );
+
+        let builder =
+            EditChannel::new().rate_limit_per_user(active_slowmode.previous_slowmode_rate);
+        match channel_id.edit(&scheduler.http, builder).await {
+            Ok(channel) => {
+                ModLogEntry::new("Slowmode expired", scheduler.bot_user_id)
+                    .channel(channel_id)
+                    .field(
+                        "Restored rate",
+                        format!("{} seconds", active_slowmode.previous_slowmode_rate),
+                    )
+                    .post(&scheduler.http, scheduler.mod_log_channel(channel.guild_id))
+                    .await;
+            },
+            // The channel has been deleted
+            Err(serenity::Error::Http(e)) if e.status_code().map(|s| s.as_u16()) == Some(404) => {
+                log::info!("Channel {} with active slowmode doesn't exist anymore", channel_id);
+            },
+            // Keep the entry around so that restoring is retried
+            Err(e) => {
+                log::warn!("Failed to restore slowmode rate of {}: {}", channel_id, e);
+                continue;
+            },
+        }
+
+        // If the invocation time differs, this slowmode invocation has been overwritten in the
+        // meantime and the new one must stay
+        sqlx::query("DELETE FROM active_slowmodes WHERE channel_id = ? AND invocation_time = ?")
+            .bind(active_slowmode.channel_id)
+            .bind(active_slowmode.invocation_time)
+            .execute(&scheduler.database)
+            .await?;
+    }
 
     Ok(())
 }
@@ -95,7 +225,8 @@ async fn restore_slowmode_rate(ctx: Context<'_>) -> Result<(), Error> {
 ///
 /// After the specified duration, the slowmode will be reset to previous level. Invoke the command \
 /// with duration set to zero to immediately lift slowmode. If the command is invoked while an
-/// existing invocation is running, the running invocation will be overwritten.
+/// existing invocation is running, the running invocation will be overwritten. Active slowmodes
+/// are stored in the database, so they are lifted even if the bot restarts in the meantime.
 ///
 /// Default duration: 30 minutes
 /// Default rate: 15 seconds
@@ -104,11 +235,14 @@ pub async fn slowmode(
     ctx: Context<'_>,
     #[description = "How long slowmode should persist for this channel, in minutes"]
     duration: Option<u64>, // TODO: make f32 with a #[min = 0.0] attribute (once poise supports it)
-    #[description = "How many seconds a user has to wait before sending another message (0-120)"]
-    rate: Option<u64>,
+    #[description = "How many seconds a user has to wait before sending another message (0-21600)"]
+    #[min = 0]
+    #[max = 21600]
+    rate: Option<u16>,
 ) -> Result<(), Error> {
-    if !crate::checks::check_is_moderator(ctx).await? {
-        return Ok(());
+    // The limits above only apply to slash commands
+    if rate.is_some_and(|rate| rate > MAX_SLOWMODE_RATE) {
+        anyhow::bail!("The rate must be at most {} seconds", MAX_SLOWMODE_RATE);
     }
 
     if duration == Some(0) || rate == Some(0) {
@@ -116,15 +250,20 @@ pub async fn slowmode(
         return Ok(());
     }
 
-    // Register that there is an active slowmode command, or overwrite an existing entry.
-    // In the end, we can make sure that our slowmode command invocation has not been overwritten
-    // since by a new invocation
+    // Register that there is an active slowmode, or overwrite an existing entry. The scheduler
+    // lifts it once it expires, unless it has been overwritten since by a new invocation
     let (duration, rate) = register_slowmode(ctx, duration, rate).await?;
 
     // Apply slowmode
-    let builder = EditChannel::new().rate_limit_per_user(rate.try_into().unwrap());
+    let builder = EditChannel::new().rate_limit_per_user(rate);
     ctx.channel_id().edit(&ctx, builder).await?;
 
+    ModLogEntry::from_context(ctx, "Slowmode enabled")
+        .field("Duration", format!("{} minutes", duration))
+        .field("Rate", format!("{} seconds", rate))
+        .post_from_context(ctx)
+        .await;
+
     // Confirmation message
     let _: Result<_, _> = ctx
         .say(format!(
@@ -134,10 +273,5 @@ pub async fn slowmode(
         ))
         .await;
 
-    // Wait until slowmode is over
-    tokio::time::sleep(std::time::Duration::from_secs(60 * duration)).await;
-
-    restore_slowmode_rate(ctx).await?;
-
     Ok(())
 }
@@ -0,0 +1,169 @@
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+
+use super::{record_case, CaseKind, Duration, ModLogEntry};
+use crate::types::Context;
+
+#[derive(Debug, sqlx::FromRow)]
+struct ScheduledUnban {
+    guild_id: i64,
+    user_id: i64,
+}
+
+/// Bans a user for a limited time (moderator only)
+///
+/// ?tempban @user <duration> [reason]
+///
+/// The duration looks like `12h`, `7d` or `2w`. The unban is stored in the database, so it \
+/// happens even if the bot was restarted in the meantime. Banning a user again overwrites the \
+/// previously scheduled unban.
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Moderation")]
+pub async fn tempban(
+    ctx: Context<'_>,
+    #[description = "User to ban"] user: serenity::User,
+    #[description = "How long the ban lasts, e.g. 12h, 7d or 2w"] duration: Duration,
+    #[description = "Why the user is banned"]
+    #[rest]
+    reason: Option<String>,
+) -> Result<(), Error> {
+    let guild_id =
+        ctx.guild_id().ok_or(anyhow::anyhow!("This command only works inside guilds"))?;
+
+    let unban_at = chrono::Utc::now()
+        .checked_add_signed(duration.0)
+        .ok_or(anyhow::anyhow!("That duration is too long"))?;
+
+    // The unban is scheduled before banning, so that a ban never exists without its unban
+    let previous_unban_at: Option<i64> = sqlx::query_scalar(
+        "SELECT unban_at FROM scheduled_unbans WHERE guild_id = ? AND user_id = ?",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(user.id.get() as i64)
+    .fetch_optional(&ctx.data().database)
+    .await?;
+    sqlx::query(
+        "INSERT INTO scheduled_unbans (guild_id, user_id, unban_at) VALUES (?, ?, ?)
+         ON CONFLICT (guild_id, user_id) DO UPDATE SET unban_at = excluded.unban_at",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(user.id.get() as i64)
+    .bind(unban_at.timestamp())
+    .execute(&ctx.data().database)
+    .await?;
+
+    let ban_result = match &reason {
+        Some(reason) => guild_id.ban_with_reason(&ctx, user.id, 0, reason).await,
+        None => guild_id.ban(&ctx, user.id, 0).await,
+    };
+    if let Err(e) = ban_result {
+        // Restore the previously scheduled unban of a still running tempban, if any
+        let rollback = match previous_unban_at {
+            Some(previous_unban_at) => sqlx::query(
+                "UPDATE scheduled_unbans SET unban_at = ? WHERE guild_id = ? AND user_id = ?",
+            )
+            .bind(previous_unban_at),
+            None => sqlx::query("DELETE FROM scheduled_unbans WHERE guild_id = ? AND user_id = ?"),
+        };
+        if let Err(e) = rollback
+            .bind(guild_id.get() as i64)
+            .bind(user.id.get() as i64)
+            .execute(&ctx.data().database)
+            .await
+        {
+            log::warn!("Failed to remove the scheduled unban of {}: {}", user.id, e);
+        }
+        return Err(e.into());
+    }
+
+    let case_id = record_case(
+        &ctx.data().database,
+        guild_id,
+        CaseKind::Tempban,
+        user.id,
+        ctx.author().id,
+        reason.as_deref(),
+        Some(duration),
+    )
+    .await?;
+
+    ModLogEntry::from_context(ctx, "Temporary ban")
+        .target(user.id)
+        .case(case_id)
+        .field("Duration", duration.to_string())
+        .field("Expires", format!("<t:{}:f>", unban_at.timestamp()))
+        .reason(reason.as_deref())
+        .post_from_context(ctx)
+        .await;
+
+    let mut response = format!(
+        "Case #{}: banned <@{}> until <t:{}:f>",
+        case_id,
+        user.id.get(),
+        unban_at.timestamp()
+    );
+    if let Some(reason) = &reason {
+        response += &format!("\n> {}", reason);
+    }
+    ctx.say(response).await?;
+
+    Ok(())
+}
+
+/// Lifts all temporary bans which have run out, including those that ran out while the bot was
+/// offline
+pub(super) async fn lift_expired_tempbans(scheduler: &super::Scheduler) -> Result<(), Error> {
+    let now = chrono::Utc::now().timestamp();
+    let expired_tempbans: Vec<ScheduledUnban> =
+        sqlx::query_as("SELECT guild_id, user_id FROM scheduled_unbans WHERE unban_at <= ?")
+            .bind(now)
+            .fetch_all(&scheduler.database)
+            .await?;
+
+    for tempban in expired_tempbans {
+        let guild_id = serenity::GuildId::new(tempban.guild_id as u64);
+        let user_id = serenity::UserId::new(tempban.user_id as u64);
+
+        log::info!("Temporary ban of {} in {} has expired, unbanning", user_id, guild_id);
+        match guild_id.unban(&scheduler.http, user_id).await {
+            Ok(()) => {
+                let case_id = record_case(
+                    &scheduler.database,
+                    guild_id,
+                    CaseKind::Unban,
+                    user_id,
+                    scheduler.bot_user_id,
+                    Some("Temporary ban expired"),
+                    None,
+                )
+                .await?;
+
+                ModLogEntry::new("Temporary ban expired", scheduler.bot_user_id)
+                    .target(user_id)
+                    .case(case_id)
+                    .post(&scheduler.http, scheduler.mod_log_channel(guild_id))
+                    .await;
+            },
+            // The user has already been unbanned manually
+            Err(serenity::Error::Http(e)) if e.status_code().map(|s| s.as_u16()) == Some(404) => {
+                log::info!("{} was already unbanned from {}", user_id, guild_id);
+            },
+            // Keep the entry around so that the unban is retried
+            Err(e) => {
+                log::warn!("Failed to lift temporary ban of {} in {}: {}", user_id, guild_id, e);
+                continue;
+            },
+        }
+
+        // The unban_at check prevents deleting a tempban that was renewed in the meantime
+        sqlx::query(
+            "DELETE FROM scheduled_unbans WHERE guild_id = ? AND user_id = ? AND unban_at <= ?",
+        )
+        .bind(tempban.guild_id)
+        .bind(tempban.user_id)
+        .bind(now)
+        .execute(&scheduler.database)
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use anyhow::Error;
+use poise::serenity_prelude::{self as serenity, EditMember};
+
+use super::{record_case, CaseKind, Duration, ModLogEntry};
+use crate::types::Context;
+
+/// Discord doesn't allow timeouts longer than this
+const MAX_TIMEOUT_DAYS: i64 = 28;
+
+/// Times out a member so they can't chat or react for a while (moderator only)
+///
+/// ?timeout @user <duration> [reason]
+///
+/// The duration looks like `30m`, `2h` or `1d12h`. Discord allows timeouts of up to 28 days. The \
+/// timeout is recorded as a case in the user's moderation history.
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Moderation")]
+pub async fn timeout(
+    ctx: Context<'_>,
+    #[description = "Member to time out"] member: serenity::Member,
+    #[description = "How long the timeout lasts, e.g. 30m, 2h or 1d12h"] duration: Duration,
+    #[description = "Why the member is timed out"]
+    #[rest]
+    reason: Option<String>,
+) -> Result<(), Error> {
+    if duration.0 > chrono::Duration::days(MAX_TIMEOUT_DAYS) {
+        anyhow::bail!("Timeouts can't be longer than {} days", MAX_TIMEOUT_DAYS);
+    }
+
+    let until = chrono::Utc::now() + duration.0;
+    let mut builder = EditMember::new().disable_communication_until_datetime(until.into());
+    if let Some(reason) = &reason {
+        builder = builder.audit_log_reason(reason);
+    }
+    member.guild_id.edit_member(&ctx, member.user.id, builder).await?;
+
+    let case_id = record_case(
+        &ctx.data().database,
+        member.guild_id,
+        CaseKind::Timeout,
+        member.user.id,
+        ctx.author().id,
+        reason.as_deref(),
+        Some(duration),
+    )
+    .await?;
+
+    ModLogEntry::from_context(ctx, "Timeout")
+        .target(member.user.id)
+        .case(case_id)
+        .field("Duration", duration.to_string())
+        .reason(reason.as_deref())
+        .post_from_context(ctx)
+        .await;
+
+    let mut response =
+        format!("Case #{}: timed out <@{}> for {}", case_id, member.user.id.get(), duration);
+    if let Some(reason) = &reason {
+        response += &format!("\n> {}", reason);
+    }
+    ctx.say(response).await?;
+
+    Ok(())
+}
+
+/// Lifts a member's timeout (moderator only)
+///
+/// ?untimeout @user [reason]
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Moderation")]
+pub async fn untimeout(
+    ctx: Context<'_>,
+    #[description = "Member whose timeout to lift"] member: serenity::Member,
+    #[description = "Why the timeout is lifted"]
+    #[rest]
+    reason: Option<String>,
+) -> Result<(), Error> {
+    if member.communication_disabled_until.is_none_or(|until| *until <= chrono::Utc::now()) {
+        anyhow::bail!("{} is not timed out", member.user.tag());
+    }
+
+    let mut builder = EditMember::new().enable_communication();
+    if let Some(reason) = &reason {
+        builder = builder.audit_log_reason(reason);
+    }
+    member.guild_id.edit_member(&ctx, member.user.id, builder).await?;
+
+    let case_id = record_case(
+        &ctx.data().database,
+        member.guild_id,
+        CaseKind::Untimeout,
+        member.user.id,
+        ctx.author().id,
+        reason.as_deref(),
+        None,
+    )
+    .await?;
+
+    ModLogEntry::from_context(ctx, "Timeout lifted")
+        .target(member.user.id)
+        .case(case_id)
+        .reason(reason.as_deref())
+        .post_from_context(ctx)
+        .await;
+
+    let mut response = format!("Case #{}: lifted timeout of <@{}>", case_id, member.user.id.get());
+    if let Some(reason) = &reason {
+        response += &format!("\n> {}", reason);
+    }
+    ctx.say(response).await?;
+
+    Ok(())
+}
@@ -0,0 +1,292 @@
+//! Relay DMs to the bot into private threads in a staff channel, so that members can contact the
+//! whole moderation team at once instead of messaging individual moderators
+
+use anyhow::Error;
+use poise::serenity_prelude::{
+    self as serenity, CreateAllowedMentions, CreateEmbed, CreateMessage, CreateThread,
+};
+
+use crate::moderation::ModLogEntry;
+use crate::types::{Context, Data};
+
+#[derive(Debug, sqlx::FromRow)]
+struct ModmailThread {
+    id: i64,
+    user_id: i64,
+    thread_id: i64,
+}
+
+impl ModmailThread {
+    fn thread_id(&self) -> serenity::ChannelId {
+        serenity::ChannelId::new(self.thread_id as u64)
+    }
+
+    fn user_id(&self) -> serenity::UserId {
+        serenity::UserId::new(self.user_id as u64)
+    }
+}
+
+async fn find_open_thread_of_user(
+    database: &sqlx::SqlitePool,
+    user_id: serenity::UserId,
+) -> Result<Option<ModmailThread>, Error> {
+    Ok(sqlx::query_as(
+        "SELECT id, user_id, thread_id FROM modmail_threads
+         WHERE user_id = ? AND closed_at IS NULL",
+    )
+    .bind(user_id.get() as i64)
+    .fetch_optional(database)
+    .await?)
+}
+
+async fn mark_closed(database: &sqlx::SqlitePool, modmail_thread_id: i64) -> Result<(), Error> {
+    sqlx::query("UPDATE modmail_threads SET closed_at = ? WHERE id = ?")
+        .bind(chrono::Utc::now().timestamp())
+        .bind(modmail_thread_id)
+        .execute(database)
+        .await?;
+    Ok(())
+}
+
+/// Creates the staff thread for a new conversation and pings the moderators in it
+async fn open_thread(
+    ctx: &serenity::Context,
+    data: &Data,
+    modmail_channel: serenity::ChannelId,
+    user: &serenity::User,
+) -> Result<ModmailThread, Error> {
+    let builder = CreateThread::new(format!("Modmail {}", user.tag()))
+        .kind(serenity::ChannelType::PrivateThread);
+    let thread = modmail_channel.create_thread(ctx, builder).await?;
+
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO modmail_threads (user_id, thread_id, opened_at) VALUES (?, ?, ?)
+         RETURNING id",
+    )
+    .bind(user.id.get() as i64)
+    .bind(thread.id.get() as i64)
+    .bind(chrono::Utc::now().timestamp())
+    .fetch_one(&data.database)
+    .await?;
+
+    // The user is only mentioned inside the embed, since mentioning them in the message content
+    // would add them to the private thread
+    let embed = CreateEmbed::new()
+        .thumbnail(user.face())
+        .description(format!("<@{}> {} ({})", user.id, user.tag(), user.id))
+        .field(
+            "Account created",
+            format!("<t:{0}:f> (<t:{0}:R>)", user.created_at().unix_timestamp()),
+            true,
+        )
+        .color(crate::types::EMBED_COLOR);
+    let settings = data.settings(thread.guild_id);
+    let builder = CreateMessage::new()
+        .content(format!(
+            "Hey {}, a member opened a modmail conversation. Answer with `?reply <message>`, or \
+             `?areply <message>` to answer anonymously, and end it with `?close`.",
+            settings.mod_role_mention()
+        ))
+        .embed(embed)
+        .allowed_mentions(CreateAllowedMentions::new().roles(settings.mod_role_id));
+    thread.send_message(ctx, builder).await?;
+
+    Ok(ModmailThread { id, user_id: user.id.get() as i64, thread_id: thread.id.get() as i64 })
+}
+
+async fn relay_to_thread(
+    ctx: &serenity::Context,
+    thread_id: serenity::ChannelId,
+    message: &serenity::Message,
+) -> Result<(), serenity::Error> {
+    let (files, attachments) = crate::helpers::copy_attachments(&message.attachments).await;
+
+    let mut embed = CreateEmbed::new()
+        .author(
+            serenity::CreateEmbedAuthor::new(message.author.tag()).icon_url(message.author.face()),
+        )
+        .timestamp(message.timestamp)
+        .color(crate::types::EMBED_COLOR);
+    if !message.content.is_empty() {
+        embed = embed.description(&message.content);
+    }
+    if !attachments.is_empty() {
+        embed = embed.field(
+            "Attachments",
+            crate::helpers::truncate_for_field(&attachments.join("\n")),
+            false,
+        );
+    }
+
+    thread_id.send_message(ctx, CreateMessage::new().embed(embed).add_files(files)).await?;
+    Ok(())
+}
+
+/// Relays a DM to the bot into the user's modmail thread, opening one if necessary
+pub async fn on_direct_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<(), Error> {
+    let Some(modmail_channel) = data.config.get().modmail_channel else {
+        return Ok(());
+    };
+    if message.guild_id.is_some() || message.author.bot {
+        return Ok(());
+    }
+    // Commands used in DMs, like `?play`, are answered by the framework instead
+    if crate::config::strip_prefix(message, data).await?.is_some() {
+        return Ok(());
+    }
+
+    // Members often send several messages in quick succession, which must not open several
+    // threads
+    let user_lock =
+        data.modmail_locks.lock().unwrap().entry(message.author.id).or_default().clone();
+    let result = {
+        let _guard = user_lock.lock().await;
+        relay_direct_message(ctx, data, modmail_channel, message).await
+    };
+
+    // The lock is only cloned while the map is locked, so nobody else is waiting for it if the
+    // map holds the only other reference
+    let mut locks = data.modmail_locks.lock().unwrap();
+    if std::sync::Arc::strong_count(&user_lock) == 2 {
+        locks.remove(&message.author.id);
+    }
+
+    result
+}
+
+async fn relay_direct_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    modmail_channel: serenity::ChannelId,
+    message: &serenity::Message,
+) -> Result<(), Error> {
+    let (thread, is_new) = match find_open_thread_of_user(&data.database, message.author.id).await?
+    {
+        Some(thread) => (thread, false),
+        None => (open_thread(ctx, data, modmail_channel, &message.author).await?, true),
+    };
+
+    match relay_to_thread(ctx, thread.thread_id(), message).await {
+        // The thread was deleted by hand, so the conversation continues in a new one
+        Err(serenity::Error::Http(e)) if e.status_code().map(|s| s.as_u16()) == Some(404) => {
+            log::info!("Modmail thread {} doesn't exist anymore", thread.thread_id);
+            mark_closed(&data.database, thread.id).await?;
+            let thread = open_thread(ctx, data, modmail_channel, &message.author).await?;
+            relay_to_thread(ctx, thread.thread_id(), message).await?;
+        },
+        result => result?,
+    }
+
+    if is_new {
+        let builder = CreateMessage::new().content(
+            "Your message has been forwarded to the moderators. They will answer you here.",
+        );
+        message.channel_id.send_message(ctx, builder).await?;
+    }
+    message.react(ctx, '✅').await?;
+
+    Ok(())
+}
+
+async fn find_open_thread(ctx: Context<'_>) -> Result<ModmailThread, Error> {
+    let thread: Option<ModmailThread> = sqlx::query_as(
+        "SELECT id, user_id, thread_id FROM modmail_threads
+         WHERE thread_id = ? AND closed_at IS NULL",
+    )
+    .bind(ctx.channel_id().get() as i64)
+    .fetch_optional(&ctx.data().database)
+    .await?;
+
+    thread.ok_or(anyhow::anyhow!("This isn't an open modmail thread"))
+}
+
+async fn reply_to_user(ctx: Context<'_>, message: String, anonymous: bool) -> Result<(), Error> {
+    let thread = find_open_thread(ctx).await?;
+
+    let author = if anonymous {
+        serenity::CreateEmbedAuthor::new("Moderators")
+    } else {
+        serenity::CreateEmbedAuthor::new(&ctx.author().name).icon_url(ctx.author().face())
+    };
+    let embed = CreateEmbed::new()
+        .author(author)
+        .description(message)
+        .timestamp(serenity::Timestamp::now())
+        .color(crate::types::EMBED_COLOR);
+
+    thread
+        .user_id()
+        .direct_message(ctx, CreateMessage::new().embed(embed.clone()))
+        .await
+        .map_err(|e| anyhow::anyhow!("Couldn't send the message to the member: {}", e))?;
+
+    // Show in the thread what the member received
+    let footer = if anonymous {
+        format!("Sent anonymously by {}", ctx.author().name)
+    } else {
+        "Sent".to_owned()
+    };
+    let embed = embed.footer(serenity::CreateEmbedFooter::new(footer));
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Answers the member of this modmail thread (moderator only)
+///
+/// ?reply <message>
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Modmail")]
+pub async fn reply(
+    ctx: Context<'_>,
+    #[description = "Message to the member"]
+    #[rest]
+    message: String,
+) -> Result<(), Error> {
+    reply_to_user(ctx, message, false).await
+}
+
+/// Answers the member of this modmail thread without revealing your name (moderator only)
+///
+/// ?areply <message>
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Modmail")]
+pub async fn areply(
+    ctx: Context<'_>,
+    #[description = "Message to the member"]
+    #[rest]
+    message: String,
+) -> Result<(), Error> {
+    reply_to_user(ctx, message, true).await
+}
+
+/// Ends the conversation of this modmail thread and archives it (moderator only)
+///
+/// The member is notified. If they send another DM, a new thread is opened.
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Modmail")]
+pub async fn close(ctx: Context<'_>) -> Result<(), Error> {
+    let thread = find_open_thread(ctx).await?;
+    mark_closed(&ctx.data().database, thread.id).await?;
+
+    let builder = CreateMessage::new().content(
+        "Your conversation with the moderators has been closed. Send another message to open a \
+         new one.",
+    );
+    if let Err(e) = thread.user_id().direct_message(ctx, builder).await {
+        log::info!("Couldn't notify {} about closed modmail: {}", thread.user_id, e);
+    }
+
+    ModLogEntry::from_context(ctx, "Modmail closed")
+        .target(thread.user_id())
+        .post_from_context(ctx)
+        .await;
+
+    ctx.say("Modmail conversation closed").await?;
+
+    let builder = serenity::EditThread::new().archived(true).locked(true);
+    thread.thread_id().edit_thread(ctx, builder).await?;
+
+    Ok(())
+}
@@ -3,10 +3,94 @@ use std::str::FromStr;
 
 use anyhow::{anyhow, bail, Error};
 use reqwest::header;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::types::Context;
 
+/// Endpoints of the playground API, relative to the configured base URL
+#[derive(Debug, Clone, Copy)]
+pub enum Endpoint {
+    Execute,
+    Miri,
+    MacroExpansion,
+    Clippy,
+    Format,
+    Compile,
+    Gist,
+}
+
+impl Endpoint {
+    pub const ALL: [Self; 7] = [
+        Self::Execute,
+        Self::Miri,
+        Self::MacroExpansion,
+        Self::Clippy,
+        Self::Format,
+        Self::Compile,
+        Self::Gist,
+    ];
+
+    pub fn path(self) -> &'static str {
+        match self {
+            Self::Execute => "execute",
+            Self::Miri => "miri",
+            Self::MacroExpansion => "macro-expansion",
+            Self::Clippy => "clippy",
+            Self::Format => "format",
+            Self::Compile => "compile",
+            Self::Gist => "meta/gist/",
+        }
+    }
+}
+
+pub fn endpoint_url(base_url: &str, endpoint: Endpoint) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), endpoint.path())
+}
+
+/// Sends a request to the configured playground. Errors name the endpoint, so that it's visible
+/// which part of a self-hosted playground is down
+pub async fn post<T: DeserializeOwned>(
+    ctx: Context<'_>,
+    endpoint: Endpoint,
+    request: &(impl Serialize + Sync),
+) -> Result<T, Error> {
+    post_to(&ctx.data().http, &ctx.data().config.get().playground.url, endpoint, request).await
+}
+
+/// How long a playground request may take, including compiling and running the code. Without a
+/// limit, requests to an unreachable playground would only fail after the OS gives up connecting
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+pub async fn post_to<T: DeserializeOwned>(
+    http: &reqwest::Client,
+    base_url: &str,
+    endpoint: Endpoint,
+    request: &(impl Serialize + Sync),
+) -> Result<T, Error> {
+    let url = endpoint_url(base_url, endpoint);
+
+    let response = http
+        .post(&url)
+        .header(header::REFERER, "https://discord.gg/rust-lang-community")
+        .json(request)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| anyhow!("The playground endpoint <{}> is unreachable: {}", url, e))?;
+
+    // Failed executions are reported with an error status and a JSON body, so the status only
+    // matters if there's no body
+    let status = response.status();
+    response.json().await.map_err(|e| {
+        if status.is_success() {
+            anyhow!("The playground endpoint <{}> sent an invalid response: {}", url, e)
+        } else {
+            anyhow!("The playground endpoint <{}> responded with {}", url, status)
+        }
+    })
+}
+
 pub struct CommandFlags {
     pub channel: Channel,
     pub mode: Mode,
@@ -53,6 +137,7 @@ pub struct FormatRequest<'a> {
 pub struct FormatResponse {
     pub success: bool,
     pub code: String,
+    #[serde(default, rename = "stdout")]
     pub _stdout: String,
     pub stderr: String,
 }
@@ -73,44 +158,58 @@ pub struct CompileRequest<'a> {
     pub tests: bool,
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AssemblyFlavour {
     #[default]
     Intel,
-    #[allow(dead_code)]
     Att,
 }
 
-#[derive(Debug, Default, Serialize)]
+impl FromStr for AssemblyFlavour {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "intel" => Ok(AssemblyFlavour::Intel),
+            "att" => Ok(AssemblyFlavour::Att),
+            _ => bail!("invalid assembly flavor `{}`", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum DemangleAssembly {
     #[default]
     Demangle,
-    #[allow(dead_code)]
     Mangle,
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ProcessAssembly {
     #[default]
     Filter,
-    #[allow(dead_code)]
     Raw,
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "snake_case")]
-#[allow(unused)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub enum CompileTarget {
+    #[serde(rename = "asm")]
+    Assembly,
+    #[serde(rename = "llvm-ir")]
+    LlvmIr,
+    #[serde(rename = "mir")]
     Mir,
+    #[serde(rename = "hir")]
+    Hir,
 }
 
-#[allow(unused)]
+/// The generated output is in the `code` field
 pub type CompileResponse = FormatResponse;
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[allow(unused)]
 pub enum Channel {
@@ -119,6 +218,16 @@ pub enum Channel {
     Nightly,
 }
 
+impl Channel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+            Channel::Nightly => "nightly",
+        }
+    }
+}
+
 impl FromStr for Channel {
     type Err = Error;
 
@@ -132,7 +241,7 @@ impl FromStr for Channel {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Edition {
     #[serde(rename = "2015")]
     E2015,
@@ -140,6 +249,21 @@ pub enum Edition {
     E2018,
     #[serde(rename = "2021")]
     E2021,
+    #[serde(rename = "2024")]
+    E2024,
+}
+
+impl Edition {
+    pub const ALL: [Self; 4] = [Self::E2015, Self::E2018, Self::E2021, Self::E2024];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
 }
 
 impl FromStr for Edition {
@@ -150,6 +274,7 @@ impl FromStr for Edition {
             "2015" => Ok(Edition::E2015),
             "2018" => Ok(Edition::E2018),
             "2021" => Ok(Edition::E2021),
+            "2024" => Ok(Edition::E2024),
             _ => bail!("invalid edition `{}`", s),
         }
     }
@@ -164,13 +289,22 @@ pub enum CrateType {
     Library,
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Mode {
     Debug,
     Release,
 }
 
+impl Mode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::Debug => "debug",
+            Mode::Release => "release",
+        }
+    }
+}
+
 impl FromStr for Mode {
     type Err = Error;
 
@@ -218,57 +352,20 @@ pub async fn post_gist(ctx: Context<'_>, code: &str) -> Result<String, Error> {
     let mut payload = HashMap::new();
     payload.insert("code", code);
 
-    let resp = ctx
-        .data()
-        .http
-        .post("https://play.rust-lang.org/meta/gist/")
-        .header(header::REFERER, "https://discord.gg/rust-lang-community")
-        .json(&payload)
-        .send()
-        .await?;
-
-    let mut resp: HashMap<String, String> = resp.json().await?;
+    let mut resp: HashMap<String, String> = post(ctx, Endpoint::Gist, &payload).await?;
     log::info!("gist response: {:?}", resp);
 
     let gist_id = resp.remove("id").ok_or(anyhow!("no gist found"))?;
     Ok(gist_id)
 }
 
-pub fn url_from_gist(flags: &CommandFlags, gist_id: &str) -> String {
+pub fn url_from_gist(base_url: &str, flags: &CommandFlags, gist_id: &str) -> String {
     format!(
-        "https://play.rust-lang.org/?version={}&mode={}&edition={}&gist={}",
-        match flags.channel {
-            Channel::Nightly => "nightly",
-            Channel::Beta => "beta",
-            Channel::Stable => "stable",
-        },
-        match flags.mode {
-            Mode::Debug => "debug",
-            Mode::Release => "release",
-        },
-        match flags.edition {
-            Edition::E2015 => "2015",
-            Edition::E2018 => "2018",
-            Edition::E2021 => "2021",
-        },
+        "{}/?version={}&mode={}&edition={}&gist={}",
+        base_url.trim_end_matches('/'),
+        flags.channel.as_str(),
+        flags.mode.as_str(),
+        flags.edition.as_str(),
         gist_id
     )
 }
-
-pub async fn apply_online_rustfmt(
-    ctx: Context<'_>,
-    code: &str,
-    edition: Edition,
-) -> Result<PlayResult, Error> {
-    let result = ctx
-        .data()
-        .http
-        .post("https://play.rust-lang.org/format")
-        .json(&FormatRequest { code, edition })
-        .send()
-        .await?
-        .json::<FormatResponse>()
-        .await?;
-
-    Ok(PlayResult { success: result.success, stdout: result.code, stderr: result.stderr })
-}
@@ -0,0 +1,147 @@
+//! Where the playground commands run code: on a rust-playground instance or in a local sandbox
+
+mod sandbox;
+
+use anyhow::Error;
+
+use crate::types::Context;
+
+use super::api::*;
+pub use sandbox::{LocalSandbox, SandboxConfig};
+
+/// Which backend the playground commands use, configured in the `[playground]` section
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// The rust-playground instance at the configured URL
+    #[default]
+    Playground,
+    /// The local sandbox configured in `[playground.local]`
+    Local,
+}
+
+/// Runs the requests of the playground commands. Compile errors and failing programs are
+/// successful results with `success: false`, errors mean that the backend itself failed
+#[async_trait::async_trait]
+pub trait ExecutionBackend: Send + Sync {
+    async fn execute(&self, request: &PlaygroundRequest<'_>) -> Result<PlayResult, Error>;
+    async fn miri(&self, request: &MiriRequest<'_>) -> Result<PlayResult, Error>;
+    async fn macro_expansion(
+        &self,
+        request: &MacroExpansionRequest<'_>,
+    ) -> Result<PlayResult, Error>;
+    async fn clippy(&self, request: &ClippyRequest<'_>) -> Result<PlayResult, Error>;
+    /// Returns the formatted code in stdout
+    async fn format(&self, request: &FormatRequest<'_>) -> Result<PlayResult, Error>;
+    async fn compile(&self, request: &CompileRequest<'_>) -> Result<CompileResponse, Error>;
+}
+
+/// A rust-playground instance, like https://play.rust-lang.org
+pub struct Playground {
+    pub http: reqwest::Client,
+    pub url: String,
+}
+
+#[async_trait::async_trait]
+impl ExecutionBackend for Playground {
+    async fn execute(&self, request: &PlaygroundRequest<'_>) -> Result<PlayResult, Error> {
+        post_to(&self.http, &self.url, Endpoint::Execute, request).await
+    }
+
+    async fn miri(&self, request: &MiriRequest<'_>) -> Result<PlayResult, Error> {
+        post_to(&self.http, &self.url, Endpoint::Miri, request).await
+    }
+
+    async fn macro_expansion(
+        &self,
+        request: &MacroExpansionRequest<'_>,
+    ) -> Result<PlayResult, Error> {
+        post_to(&self.http, &self.url, Endpoint::MacroExpansion, request).await
+    }
+
+    async fn clippy(&self, request: &ClippyRequest<'_>) -> Result<PlayResult, Error> {
+        post_to(&self.http, &self.url, Endpoint::Clippy, request).await
+    }
+
+    async fn format(&self, request: &FormatRequest<'_>) -> Result<PlayResult, Error> {
+        let result: FormatResponse =
+            post_to(&self.http, &self.url, Endpoint::Format, request).await?;
+        Ok(PlayResult { success: result.success, stdout: result.code, stderr: result.stderr })
+    }
+
+    async fn compile(&self, request: &CompileRequest<'_>) -> Result<CompileResponse, Error> {
+        post_to(&self.http, &self.url, Endpoint::Compile, request).await
+    }
+}
+
+/// Uses the playground, and the local sandbox while the playground is unreachable
+struct WithFallback {
+    primary: Playground,
+    fallback: LocalSandbox,
+}
+
+macro_rules! with_fallback {
+    ($self:ident, $method:ident, $request:ident) => {
+        match $self.primary.$method($request).await {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                log::warn!("Playground failed, falling back to the local sandbox: {}", e);
+                $self.fallback.$method($request).await
+            },
+        }
+    };
+}
+
+#[async_trait::async_trait]
+impl ExecutionBackend for WithFallback {
+    async fn execute(&self, request: &PlaygroundRequest<'_>) -> Result<PlayResult, Error> {
+        with_fallback!(self, execute, request)
+    }
+
+    async fn miri(&self, request: &MiriRequest<'_>) -> Result<PlayResult, Error> {
+        with_fallback!(self, miri, request)
+    }
+
+    async fn macro_expansion(
+        &self,
+        request: &MacroExpansionRequest<'_>,
+    ) -> Result<PlayResult, Error> {
+        with_fallback!(self, macro_expansion, request)
+    }
+
+    async fn clippy(&self, request: &ClippyRequest<'_>) -> Result<PlayResult, Error> {
+        with_fallback!(self, clippy, request)
+    }
+
+    async fn format(&self, request: &FormatRequest<'_>) -> Result<PlayResult, Error> {
+        with_fallback!(self, format, request)
+    }
+
+    async fn compile(&self, request: &CompileRequest<'_>) -> Result<CompileResponse, Error> {
+        with_fallback!(self, compile, request)
+    }
+}
+
+/// The backend selected in the current configuration
+pub fn backend(ctx: Context<'_>) -> Box<dyn ExecutionBackend> {
+    let config = ctx.data().config.get();
+    let playground =
+        Playground { http: ctx.data().http.clone(), url: config.playground.url.clone() };
+    let local = LocalSandbox { config: config.playground.local.clone() };
+
+    match config.playground.backend {
+        BackendKind::Playground if config.playground.fallback_to_local => {
+            Box::new(WithFallback { primary: playground, fallback: local })
+        },
+        BackendKind::Playground => Box::new(playground),
+        BackendKind::Local => Box::new(local),
+    }
+}
+
+pub async fn apply_rustfmt(
+    ctx: Context<'_>,
+    code: &str,
+    edition: Edition,
+) -> Result<PlayResult, Error> {
+    backend(ctx).format(&FormatRequest { code, edition }).await
+}
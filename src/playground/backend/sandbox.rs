@@ -0,0 +1,335 @@
+//! Runs cargo, rustc and rustfmt locally inside [nsjail](https://github.com/google/nsjail), which
+//! puts them into fresh namespaces without network access, with a seccomp filter, resource limits
+//! and a time limit
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+use super::super::api::*;
+use super::ExecutionBackend;
+
+/// Where the project is mounted read-only inside the sandbox
+const SOURCE_DIR: &str = "/source";
+/// The size-limited tmpfs the project is copied to and built in, so that nothing is written to the
+/// host's disk. Also makes compiler output look like the playground's, which the output
+/// post-processing relies on
+const PROJECT_DIR: &str = "/playground";
+/// Where `?asm` and `?play_llvmir` output is written to
+const EMITTED_FILE: &str = "/playground/emitted";
+
+/// Syscalls which code inside the sandbox has no business with, denied with EPERM
+const SECCOMP_POLICY: &str = "\
+ERRNO(1) {
+    ptrace, process_vm_readv, process_vm_writev, mount, umount2, pivot_root, chroot, unshare,
+    setns, swapon, swapoff, reboot, kexec_load, kexec_file_load, init_module, finit_module,
+    delete_module, bpf, perf_event_open, keyctl, add_key, request_key, acct, settimeofday,
+    clock_settime, iopl, ioperm, userfaultfd
+}
+DEFAULT ALLOW";
+
+fn home_dir(env_var: &str, default_subdir: &str) -> PathBuf {
+    std::env::var_os(env_var).map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(default_subdir)
+    })
+}
+
+/// The `[playground.local]` section of the config file
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Path of the nsjail binary
+    pub nsjail: PathBuf,
+    /// Host directories mounted read-only into the sandbox, like system libraries. Directories
+    /// that don't exist on the host are skipped
+    pub mounts: Vec<PathBuf>,
+    /// rustup and cargo directories with the toolchains, mounted read-only at the same path.
+    /// Default to the bot's RUSTUP_HOME and CARGO_HOME
+    pub rustup_home: PathBuf,
+    pub cargo_home: PathBuf,
+    /// Sysroot prepared with `cargo +nightly miri setup`, required by `?miri`
+    pub miri_sysroot: Option<PathBuf>,
+    /// Wall clock and CPU time limit of a run, including compilation
+    #[serde(deserialize_with = "crate::config::deserialize_std_duration")]
+    pub time_limit: Duration,
+    /// Address space limit of every process in the sandbox
+    pub memory_limit_mb: u64,
+    /// Memory limit of the whole sandbox, including the project tmpfs, enforced with a cgroup v2.
+    /// nsjail needs to be able to create cgroups for this
+    pub cgroup_memory_limit_mb: u64,
+    /// Process limit of the whole sandbox, enforced with a cgroup v2
+    pub max_processes: u64,
+    /// Size of the tmpfs the project is built in
+    pub disk_limit_mb: u64,
+    /// Output beyond this is discarded
+    pub max_output_bytes: usize,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            nsjail: "nsjail".into(),
+            mounts: ["/bin", "/lib", "/lib64", "/usr", "/etc"].map(PathBuf::from).into(),
+            rustup_home: home_dir("RUSTUP_HOME", ".rustup"),
+            cargo_home: home_dir("CARGO_HOME", ".cargo"),
+            miri_sysroot: None,
+            time_limit: Duration::from_secs(15),
+            memory_limit_mb: 4096,
+            cgroup_memory_limit_mb: 2048,
+            max_processes: 64,
+            disk_limit_mb: 512,
+            max_output_bytes: 64 * 1024,
+        }
+    }
+}
+
+/// A throwaway cargo project. It only holds the sources, the build happens inside the sandbox
+struct Project {
+    dir: tempfile::TempDir,
+}
+
+impl Project {
+    fn new(code: &str, edition: Edition, crate_type: CrateType) -> Result<Self, Error> {
+        let dir = tempfile::Builder::new().prefix("playground").tempdir()?;
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"playground\"\nversion = \"0.0.1\"\nedition = \"{}\"\n",
+                edition.as_str()
+            ),
+        )?;
+        std::fs::create_dir(dir.path().join("src"))?;
+        let source_file = match crate_type {
+            CrateType::Binary => "main.rs",
+            CrateType::Library => "lib.rs",
+        };
+        std::fs::write(dir.path().join("src").join(source_file), code)?;
+
+        Ok(Self { dir })
+    }
+
+    fn empty() -> Result<Self, Error> {
+        Ok(Self { dir: tempfile::Builder::new().prefix("playground").tempdir()? })
+    }
+}
+
+/// Reads at most `limit` bytes and discards the rest, so that the process doesn't block on a full
+/// pipe
+async fn read_limited(mut pipe: impl AsyncRead + Unpin, limit: usize) -> std::io::Result<String> {
+    let mut output = Vec::new();
+    (&mut pipe).take(limit as u64).read_to_end(&mut output).await?;
+    tokio::io::copy(&mut pipe, &mut tokio::io::sink()).await?;
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+pub struct LocalSandbox {
+    pub config: SandboxConfig,
+}
+
+impl LocalSandbox {
+    fn nsjail_args(&self, project_dir: &Path) -> Vec<String> {
+        let config = &self.config;
+        let time_limit = config.time_limit.as_secs().max(1).to_string();
+
+        let mut args: Vec<String> = vec![
+            "--mode".into(),
+            "o".into(),
+            "--quiet".into(),
+            "--time_limit".into(),
+            time_limit.clone(),
+            "--rlimit_cpu".into(),
+            time_limit,
+            "--rlimit_as".into(),
+            config.memory_limit_mb.to_string(),
+            "--rlimit_fsize".into(),
+            "256".into(),
+            "--rlimit_nofile".into(),
+            "256".into(),
+            "--use_cgroupv2".into(),
+            "--cgroup_mem_max".into(),
+            (config.cgroup_memory_limit_mb * 1024 * 1024).to_string(),
+            "--cgroup_pids_max".into(),
+            config.max_processes.to_string(),
+            "--seccomp_string".into(),
+            SECCOMP_POLICY.into(),
+        ];
+
+        let read_only_mounts = config
+            .mounts
+            .iter()
+            .chain([&config.rustup_home, &config.cargo_home])
+            .chain(&config.miri_sysroot)
+            .filter(|path| path.exists());
+        for path in read_only_mounts {
+            args.extend(["-R".into(), path.display().to_string()]);
+        }
+        for device in ["/dev/null", "/dev/zero", "/dev/urandom"] {
+            args.extend(["-R".into(), device.into()]);
+        }
+        args.extend([
+            "-T".into(),
+            "/tmp".into(),
+            "-R".into(),
+            format!("{}:{}", project_dir.display(), SOURCE_DIR),
+            "-m".into(),
+            format!("none:{}:tmpfs:size={}M", PROJECT_DIR, config.disk_limit_mb),
+            "--cwd".into(),
+            PROJECT_DIR.into(),
+        ]);
+
+        let mut env = vec![
+            format!("PATH={}/bin:/usr/local/bin:/usr/bin:/bin", config.cargo_home.display()),
+            format!("HOME={}", PROJECT_DIR),
+            format!("RUSTUP_HOME={}", config.rustup_home.display()),
+            format!("CARGO_HOME={}", config.cargo_home.display()),
+            "CARGO_NET_OFFLINE=true".into(),
+        ];
+        if let Some(sysroot) = &config.miri_sysroot {
+            env.push(format!("MIRI_SYSROOT={}", sysroot.display()));
+        }
+        for var in env {
+            args.extend(["--env".into(), var]);
+        }
+
+        args
+    }
+
+    /// Copies the project into the sandbox's tmpfs and runs the command there
+    async fn run(
+        &self,
+        project: &Project,
+        command: &[&str],
+        stdin: Option<&str>,
+    ) -> Result<PlayResult, Error> {
+        let mut child = tokio::process::Command::new(&self.config.nsjail)
+            .args(self.nsjail_args(project.dir.path()))
+            .arg("--")
+            .args(["/bin/sh", "-c", &format!("cp -R {}/. . && exec \"$@\"", SOURCE_DIR), "sh"])
+            .args(command)
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("Couldn't start the local sandbox: {}", e))?;
+
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input.as_bytes()).await?;
+        }
+        let stdout = read_limited(child.stdout.take().unwrap(), self.config.max_output_bytes);
+        let stderr = read_limited(child.stderr.take().unwrap(), self.config.max_output_bytes);
+
+        // nsjail enforces the time limit itself, this only guards against nsjail hanging
+        let (stdout, mut stderr, status) =
+            tokio::time::timeout(self.config.time_limit + Duration::from_secs(5), async {
+                tokio::try_join!(stdout, stderr, child.wait())
+            })
+            .await
+            .map_err(|_| anyhow!("The local sandbox didn't exit in time"))??;
+
+        // nsjail exits with 128 + signal number if the process was killed, by SIGKILL when the
+        // time limit ran out or SIGXCPU when the CPU time limit did
+        if matches!(status.code(), Some(137 | 152)) {
+            stderr += &format!(
+                "\nerror: killed after exceeding the time limit of {} seconds\n",
+                self.config.time_limit.as_secs()
+            );
+        }
+
+        Ok(PlayResult { success: status.success(), stdout, stderr })
+    }
+}
+
+#[async_trait::async_trait]
+impl ExecutionBackend for LocalSandbox {
+    async fn execute(&self, request: &PlaygroundRequest<'_>) -> Result<PlayResult, Error> {
+        let project = Project::new(request.code, request.edition, request.crate_type)?;
+        let toolchain = format!("+{}", request.channel.as_str());
+        let subcommand = match (request.tests, request.crate_type) {
+            (true, _) => "test",
+            (false, CrateType::Binary) => "run",
+            (false, CrateType::Library) => "build",
+        };
+
+        let mut command = vec!["cargo", &toolchain, subcommand];
+        if let Mode::Release = request.mode {
+            command.push("--release");
+        }
+        self.run(&project, &command, None).await
+    }
+
+    async fn miri(&self, request: &MiriRequest<'_>) -> Result<PlayResult, Error> {
+        let project = Project::new(request.code, request.edition, CrateType::Binary)?;
+        self.run(&project, &["cargo", "+nightly", "miri", "run"], None).await
+    }
+
+    async fn macro_expansion(
+        &self,
+        request: &MacroExpansionRequest<'_>,
+    ) -> Result<PlayResult, Error> {
+        let project = Project::new(request.code, request.edition, CrateType::Binary)?;
+        self.run(&project, &["cargo", "+nightly", "rustc", "--", "-Zunpretty=expanded"], None).await
+    }
+
+    async fn clippy(&self, request: &ClippyRequest<'_>) -> Result<PlayResult, Error> {
+        let project = Project::new(request.code, request.edition, request.crate_type)?;
+        self.run(&project, &["cargo", "+stable", "clippy"], None).await
+    }
+
+    async fn format(&self, request: &FormatRequest<'_>) -> Result<PlayResult, Error> {
+        let project = Project::empty()?;
+        let command = ["rustfmt", "+stable", "--edition", request.edition.as_str()];
+        self.run(&project, &command, Some(request.code)).await
+    }
+
+    /// Unlike the playground, this neither demangles nor filters assembly
+    async fn compile(&self, request: &CompileRequest<'_>) -> Result<CompileResponse, Error> {
+        let project = Project::new(request.code, request.edition, request.crate_type)?;
+        // -Zunpretty is only available on nightly
+        let toolchain = match request.target {
+            CompileTarget::Mir | CompileTarget::Hir => "+nightly".to_owned(),
+            CompileTarget::Assembly | CompileTarget::LlvmIr => {
+                format!("+{}", request.channel.as_str())
+            },
+        };
+
+        // Emitted files are printed after compilation, as the build directory is only accessible
+        // inside the sandbox. A single codegen unit makes rustc emit a single file
+        let print_emitted = format!("\"$@\" && cat {}", EMITTED_FILE);
+        let emit_asm = format!("--emit=asm={}", EMITTED_FILE);
+        let emit_llvm_ir = format!("--emit=llvm-ir={}", EMITTED_FILE);
+
+        let mut command = match request.target {
+            CompileTarget::Assembly | CompileTarget::LlvmIr => {
+                vec!["/bin/sh", "-c", &print_emitted, "sh", "cargo", &toolchain, "rustc"]
+            },
+            CompileTarget::Mir | CompileTarget::Hir => vec!["cargo", &toolchain, "rustc"],
+        };
+        if let Mode::Release = request.mode {
+            command.push("--release");
+        }
+        command.push("--");
+        match request.target {
+            CompileTarget::Assembly => {
+                command.extend([emit_asm.as_str(), "-Ccodegen-units=1"]);
+                if let AssemblyFlavour::Intel = request.assembly_flavor {
+                    command.push("-Cllvm-args=-x86-asm-syntax=intel");
+                }
+            },
+            CompileTarget::LlvmIr => command.extend([emit_llvm_ir.as_str(), "-Ccodegen-units=1"]),
+            CompileTarget::Mir => command.push("-Zunpretty=mir"),
+            CompileTarget::Hir => command.push("-Zunpretty=hir"),
+        }
+
+        let result = self.run(&project, &command, None).await?;
+        Ok(CompileResponse {
+            success: result.success,
+            code: result.stdout,
+            _stdout: String::new(),
+            stderr: result.stderr,
+        })
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::Error;
+
+use crate::types::Context;
+
+use super::api::*;
+use super::backend::*;
+use super::util::*;
+
+/// Code without `fn main` is compiled as a library, so that its functions aren't dropped as unused
+fn crate_type(code: &str) -> CrateType {
+    if code.contains("fn main") {
+        CrateType::Binary
+    } else {
+        CrateType::Library
+    }
+}
+
+// asm, mir, hir and play_llvmir work the same, apart from the compile target
+async fn compile_and_show(
+    ctx: Context<'_>,
+    mut flags: poise::KeyValueArgs,
+    code: poise::CodeBlock,
+    target: CompileTarget,
+) -> Result<(), Error> {
+    ctx.say(stub_message(ctx)).await?;
+
+    // Assembly output flags aren't known to parse_flags, so they're taken out beforehand
+    let mut assembly_flavor = AssemblyFlavour::default();
+    let mut demangle_assembly = DemangleAssembly::default();
+    let mut process_assembly = ProcessAssembly::default();
+    let mut assembly_flag_errors = String::new();
+    if let CompileTarget::Assembly = target {
+        if let Some(flavor) = flags.0.remove("flavor") {
+            match flavor.parse() {
+                Ok(flavor) => assembly_flavor = flavor,
+                Err(e) => assembly_flag_errors += &format!("{}\n", e),
+            }
+        }
+        if let Some(demangle) = flags.0.remove("demangle") {
+            match demangle.parse() {
+                Ok(true) => demangle_assembly = DemangleAssembly::Demangle,
+                Ok(false) => demangle_assembly = DemangleAssembly::Mangle,
+                Err(e) => assembly_flag_errors += &format!("{}\n", e),
+            }
+        }
+        if let Some(filter) = flags.0.remove("filter") {
+            match filter.parse() {
+                Ok(true) => process_assembly = ProcessAssembly::Filter,
+                Ok(false) => process_assembly = ProcessAssembly::Raw,
+                Err(e) => assembly_flag_errors += &format!("{}\n", e),
+            }
+        }
+    }
+    let (flags, mut flag_parse_errors) = parse_flags(&ctx.data().config.get().playground, flags);
+    flag_parse_errors += &assembly_flag_errors;
+
+    let code = &code.code;
+    let result = backend(ctx)
+        .compile(&CompileRequest {
+            assembly_flavor,
+            backtrace: false,
+            channel: flags.channel,
+            code,
+            crate_type: crate_type(code),
+            demangle_assembly,
+            edition: flags.edition,
+            mode: flags.mode,
+            process_assembly,
+            target,
+            tests: false,
+        })
+        .await?;
+
+    let result = PlayResult {
+        success: result.success,
+        stdout: result.code,
+        stderr: format_play_eval_stderr(&result.stderr, flags.warn),
+    };
+    send_reply(ctx, result, code, &flags, &flag_parse_errors).await
+}
+
+fn compile_help(command: &str, desc: &str) -> String {
+    generic_help(GenericHelp {
+        command,
+        desc,
+        mode_and_channel: true,
+        warn: true,
+        run: false,
+        example_code: "code",
+    })
+}
+
+/// Show the assembly generated by the playground
+#[poise::command(prefix_command, track_edits, help_text_fn = "asm_help", category = "Playground")]
+pub async fn asm(
+    ctx: Context<'_>,
+    flags: poise::KeyValueArgs,
+    code: poise::CodeBlock,
+) -> Result<(), Error> {
+    compile_and_show(ctx, flags, code, CompileTarget::Assembly).await
+}
+
+pub fn asm_help() -> String {
+    let mut help = compile_help(
+        "asm",
+        "Show the assembly of the code. Code without `fn main` is compiled as a library, so \
+         public functions are kept",
+    );
+    help += "- flavor: intel, att (default: intel)\n";
+    help += "- demangle: true, false (default: true)\n";
+    help += "- filter: true, false (default: true). Removes directives and unused labels\n";
+    help
+}
+
+/// Show the MIR generated by the playground
+#[poise::command(prefix_command, track_edits, help_text_fn = "mir_help", category = "Playground")]
+pub async fn mir(
+    ctx: Context<'_>,
+    flags: poise::KeyValueArgs,
+    code: poise::CodeBlock,
+) -> Result<(), Error> {
+    compile_and_show(ctx, flags, code, CompileTarget::Mir).await
+}
+
+pub fn mir_help() -> String {
+    compile_help("mir", "Show the mid-level intermediate representation (MIR) of the code")
+}
+
+/// Show the HIR generated by the playground
+#[poise::command(prefix_command, track_edits, help_text_fn = "hir_help", category = "Playground")]
+pub async fn hir(
+    ctx: Context<'_>,
+    flags: poise::KeyValueArgs,
+    code: poise::CodeBlock,
+) -> Result<(), Error> {
+    compile_and_show(ctx, flags, code, CompileTarget::Hir).await
+}
+
+pub fn hir_help() -> String {
+    compile_help("hir", "Show the high-level intermediate representation (HIR) of the code")
+}
+
+/// Show the LLVM IR generated by the playground
+#[poise::command(
+    prefix_command,
+    track_edits,
+    help_text_fn = "play_llvmir_help",
+    category = "Playground"
+)]
+pub async fn play_llvmir(
+    ctx: Context<'_>,
+    flags: poise::KeyValueArgs,
+    code: poise::CodeBlock,
+) -> Result<(), Error> {
+    compile_and_show(ctx, flags, code, CompileTarget::LlvmIr).await
+}
+
+pub fn play_llvmir_help() -> String {
+    compile_help(
+        "play_llvmir",
+        "Show the LLVM IR of the code, using the playground's toolchains instead of godbolt like \
+         `?llvmir`",
+    )
+}
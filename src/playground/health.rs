@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use anyhow::Error;
+
+use crate::types::Context;
+
+use super::api::*;
+
+/// Sends a minimal request to the endpoint and returns how long it took to respond
+async fn probe(ctx: Context<'_>, endpoint: Endpoint) -> Result<std::time::Duration, Error> {
+    let defaults = ctx.data().config.get().playground.clone();
+    let code = "fn main() {}";
+    let start = Instant::now();
+
+    match endpoint {
+        Endpoint::Execute => {
+            post::<PlayResult>(ctx, endpoint, &PlaygroundRequest {
+                code,
+                channel: defaults.channel,
+                crate_type: CrateType::Binary,
+                edition: defaults.edition,
+                mode: defaults.mode,
+                tests: false,
+            })
+            .await?;
+        },
+        Endpoint::Miri | Endpoint::MacroExpansion => {
+            post::<PlayResult>(ctx, endpoint, &MiriRequest { code, edition: defaults.edition })
+                .await?;
+        },
+        Endpoint::Clippy => {
+            post::<PlayResult>(ctx, endpoint, &ClippyRequest {
+                code,
+                edition: defaults.edition,
+                crate_type: CrateType::Binary,
+            })
+            .await?;
+        },
+        Endpoint::Format => {
+            post::<FormatResponse>(ctx, endpoint, &FormatRequest {
+                code,
+                edition: defaults.edition,
+            })
+            .await?;
+        },
+        Endpoint::Compile => {
+            post::<CompileResponse>(ctx, endpoint, &CompileRequest {
+                assembly_flavor: AssemblyFlavour::default(),
+                backtrace: false,
+                channel: defaults.channel,
+                code,
+                crate_type: CrateType::Binary,
+                demangle_assembly: DemangleAssembly::default(),
+                edition: defaults.edition,
+                mode: defaults.mode,
+                process_assembly: ProcessAssembly::default(),
+                target: CompileTarget::Mir,
+                tests: false,
+            })
+            .await?;
+        },
+        Endpoint::Gist => {
+            post::<HashMap<String, String>>(ctx, endpoint, &HashMap::from([("code", code)]))
+                .await?;
+        },
+    }
+
+    Ok(start.elapsed())
+}
+
+/// Check which endpoints of the configured playground are reachable
+///
+/// Sends a minimal request to every endpoint used by the playground commands, all at once. Note \
+/// that this creates a throwaway gist.
+#[poise::command(prefix_command, slash_command, guild_only, hide_in_help, category = "Playground")]
+pub async fn playground_health(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let mut reply = format!("Playground at <{}>:\n", ctx.data().config.get().playground.url);
+    let results =
+        poise::futures_util::future::join_all(Endpoint::ALL.map(|endpoint| probe(ctx, endpoint)))
+            .await;
+    for (endpoint, result) in Endpoint::ALL.into_iter().zip(results) {
+        match result {
+            Ok(elapsed) => {
+                reply += &format!("✅ `{}`: {} ms\n", endpoint.path(), elapsed.as_millis())
+            },
+            Err(e) => reply += &format!("❌ `{}`: {}\n", endpoint.path(), e),
+        }
+    }
+
+    ctx.say(crate::helpers::trim_text(&reply, "", async { String::new() }).await).await?;
+
+    Ok(())
+}
@@ -3,6 +3,7 @@ use anyhow::Error;
 use crate::types::Context;
 
 use super::api::*;
+use super::backend::*;
 use super::util::*;
 
 const BENCH_FUNCTION: &str = r#"
@@ -104,12 +105,9 @@ pub async fn microbench(
     // final assembled code
     let code = hoise_crate_attributes(user_code, after_crate_attrs, &after_code);
 
-    let (flags, mut flag_parse_errors) = parse_flags(flags);
-    let mut result: PlayResult = ctx
-        .data()
-        .http
-        .post("https://play.rust-lang.org/execute")
-        .json(&PlaygroundRequest {
+    let (flags, mut flag_parse_errors) = parse_flags(&ctx.data().config.get().playground, flags);
+    let mut result: PlayResult = backend(ctx)
+        .execute(&PlaygroundRequest {
             code: &code,
             channel: flags.channel,
             crate_type: CrateType::Binary,
@@ -117,9 +115,6 @@ pub async fn microbench(
             mode: Mode::Release, // benchmarks on debug don't make sense
             tests: false,
         })
-        .send()
-        .await?
-        .json()
         .await?;
 
     result.stderr = format_play_eval_stderr(&result.stderr, flags.warn);
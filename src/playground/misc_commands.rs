@@ -5,6 +5,7 @@ use anyhow::Error;
 use crate::types::Context;
 
 use super::api::*;
+use super::backend::*;
 use super::util::*;
 
 /// Run code and detect undefined behavior using Miri
@@ -21,17 +22,10 @@ pub async fn miri(
         ctx.prefix().contains("Sweat"),
         ctx.prefix().contains("OwO") || ctx.prefix().contains("Cat"),
     );
-    let (flags, flag_parse_errors) = parse_flags(flags);
-
-    let mut result: PlayResult = ctx
-        .data()
-        .http
-        .post("https://play.rust-lang.org/miri")
-        .json(&MiriRequest { code, edition: flags.edition })
-        .send()
-        .await?
-        .json()
-        .await?;
+    let (flags, flag_parse_errors) = parse_flags(&ctx.data().config.get().playground, flags);
+
+    let mut result: PlayResult =
+        backend(ctx).miri(&MiriRequest { code, edition: flags.edition }).await?;
 
     result.stderr =
         extract_relevant_lines(&result.stderr, &["Running `/playground"], &["error: aborting"])
@@ -70,16 +64,10 @@ pub async fn expand(
 
     let code = maybe_wrap(&code.code, ResultHandling::None);
     let was_fn_main_wrapped = matches!(code, Cow::Owned(_));
-    let (flags, flag_parse_errors) = parse_flags(flags);
-
-    let mut result: PlayResult = ctx
-        .data()
-        .http
-        .post("https://play.rust-lang.org/macro-expansion")
-        .json(&MacroExpansionRequest { code: &code, edition: flags.edition })
-        .send()
-        .await?
-        .json()
+    let (flags, flag_parse_errors) = parse_flags(&ctx.data().config.get().playground, flags);
+
+    let mut result: PlayResult = backend(ctx)
+        .macro_expansion(&MacroExpansionRequest { code: &code, edition: flags.edition })
         .await?;
 
     result.stderr =
@@ -89,7 +77,7 @@ pub async fn expand(
         .to_owned();
 
     if result.success {
-        match apply_online_rustfmt(ctx, &result.stdout, flags.edition).await {
+        match apply_rustfmt(ctx, &result.stdout, flags.edition).await {
             Ok(PlayResult { success: true, stdout, .. }) => result.stdout = stdout,
             Ok(PlayResult { success: false, stderr, .. }) => log::warn!(
                 "Huh, rustfmt failed even though this code successfully passed through macro \
@@ -137,16 +125,10 @@ pub async fn clippy(
         "#![allow(dead_code, clippy::let_unit_value)] {}",
         maybe_wrapped(&code.code, ResultHandling::Discard, ctx.prefix().contains("Sweat"), false,)
     );
-    let (flags, flag_parse_errors) = parse_flags(flags);
-
-    let mut result: PlayResult = ctx
-        .data()
-        .http
-        .post("https://play.rust-lang.org/clippy")
-        .json(&ClippyRequest { code, edition: flags.edition, crate_type: CrateType::Binary })
-        .send()
-        .await?
-        .json()
+    let (flags, flag_parse_errors) = parse_flags(&ctx.data().config.get().playground, flags);
+
+    let mut result: PlayResult = backend(ctx)
+        .clippy(&ClippyRequest { code, edition: flags.edition, crate_type: CrateType::Binary })
         .await?;
 
     result.stderr = extract_relevant_lines(
@@ -181,9 +163,9 @@ pub async fn fmt(
 
     let code = &maybe_wrap(&code.code, ResultHandling::None);
     let was_fn_main_wrapped = matches!(code, Cow::Owned(_));
-    let (flags, flag_parse_errors) = parse_flags(flags);
+    let (flags, flag_parse_errors) = parse_flags(&ctx.data().config.get().playground, flags);
 
-    let mut result = apply_online_rustfmt(ctx, code, flags.edition).await?;
+    let mut result = apply_rustfmt(ctx, code, flags.edition).await?;
 
     if was_fn_main_wrapped {
         result.stdout = strip_fn_main_boilerplate_from_formatted(&result.stdout);
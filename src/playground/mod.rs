@@ -1,13 +1,22 @@
 //! run rust code on the rust-lang playground
 
 mod api;
+mod backend;
 mod util;
 
+mod compile;
+mod health;
 mod microbench;
 mod misc_commands;
 mod play_eval;
 mod procmacro;
+mod test_runner;
+pub use backend::{BackendKind, SandboxConfig};
+pub use compile::*;
+pub use health::*;
 pub use microbench::*;
 pub use misc_commands::*;
 pub use play_eval::*;
 pub use procmacro::*;
+pub use test_runner::*;
+pub use util::PlaygroundDefaults;
@@ -3,6 +3,7 @@ use anyhow::Error;
 use crate::types::Context;
 
 use super::api::*;
+use super::backend::*;
 use super::util::*;
 
 // play and eval work similarly, so this function abstracts over the two
@@ -21,17 +22,14 @@ async fn play_or_eval(
         ctx.prefix().contains("Sweat"),
         ctx.prefix().contains("OwO") || ctx.prefix().contains("Cat"),
     );
-    let (mut flags, flag_parse_errors) = parse_flags(flags);
+    let (mut flags, flag_parse_errors) = parse_flags(&ctx.data().config.get().playground, flags);
 
     if force_warnings {
         flags.warn = true;
     }
 
-    let mut result: PlayResult = ctx
-        .data()
-        .http
-        .post("https://play.rust-lang.org/execute")
-        .json(&PlaygroundRequest {
+    let mut result: PlayResult = backend(ctx)
+        .execute(&PlaygroundRequest {
             code: &code,
             channel: flags.channel,
             crate_type: CrateType::Binary,
@@ -39,9 +37,6 @@ async fn play_or_eval(
             mode: flags.mode,
             tests: false,
         })
-        .send()
-        .await?
-        .json()
         .await?;
 
     result.stderr = format_play_eval_stderr(&result.stderr, flags.warn);
@@ -3,6 +3,7 @@ use anyhow::Error;
 use crate::types::Context;
 
 use super::api::*;
+use super::backend::*;
 use super::util::*;
 
 /// Compile and use a procedural macro
@@ -23,7 +24,7 @@ pub async fn procmacro(
     let macro_code = macro_code.code;
     let usage_code = maybe_wrap(&usage_code.code, ResultHandling::None);
 
-    let (flags, flag_parse_errors) = parse_flags(flags);
+    let (flags, flag_parse_errors) = parse_flags(&ctx.data().config.get().playground, flags);
 
     let mut generated_code = format!(
         stringify!(
@@ -68,11 +69,8 @@ fn main() -> std::io::Result<()> {
     Ok(())
 }"#;
 
-    let mut result: PlayResult = ctx
-        .data()
-        .http
-        .post("https://play.rust-lang.org/execute")
-        .json(&PlaygroundRequest {
+    let mut result: PlayResult = backend(ctx)
+        .execute(&PlaygroundRequest {
             code: &generated_code,
             channel: Channel::Nightly, // so that inner proc macro gets nightly too
             // These flags only apply to the glue code
@@ -81,9 +79,6 @@ fn main() -> std::io::Result<()> {
             mode: Mode::Debug,
             tests: false,
         })
-        .send()
-        .await?
-        .json()
         .await?;
 
     // funky
@@ -0,0 +1,243 @@
+use anyhow::Error;
+
+use crate::types::Context;
+
+use super::api::*;
+use super::backend::*;
+use super::util::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TestOutcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+#[derive(Debug)]
+struct TestCase {
+    name: String,
+    outcome: TestOutcome,
+    /// Output of the failed test, like the panic message of a failed assertion
+    failure: String,
+}
+
+/// Extracts the results of all tests, unit tests and doctests, from libtest's output:
+/// ```text
+/// test tests::passes ... ok
+/// test tests::fails ... FAILED
+///
+/// failures:
+///
+/// ---- tests::fails stdout ----
+/// thread 'tests::fails' panicked at src/lib.rs:9:9:
+/// assertion failed: false
+///
+///
+/// failures:
+///     tests::fails
+/// ```
+fn parse_libtest_output(stdout: &str) -> Vec<TestCase> {
+    let mut tests = Vec::new();
+    // Index of the test whose failure output is being read
+    let mut current_failure = None;
+
+    for line in stdout.lines() {
+        if let Some(name) =
+            line.strip_prefix("---- ").and_then(|header| header.strip_suffix(" stdout ----"))
+        {
+            current_failure = tests.iter().position(|test: &TestCase| test.name == name);
+        } else if line == "failures:" || line.starts_with("test result:") {
+            current_failure = None;
+        } else if let Some((name, outcome)) =
+            line.strip_prefix("test ").and_then(|test| test.split_once(" ... "))
+        {
+            let outcome = if outcome == "ok" {
+                TestOutcome::Passed
+            } else if outcome == "FAILED" {
+                TestOutcome::Failed
+            } else if outcome.starts_with("ignored") {
+                TestOutcome::Ignored
+            } else {
+                // Benchmark results
+                continue;
+            };
+            tests.push(TestCase { name: name.to_owned(), outcome, failure: String::new() });
+        } else if let Some(index) = current_failure {
+            if !line.starts_with("note: run with `RUST_BACKTRACE=1`") {
+                tests[index].failure += line;
+                tests[index].failure.push('\n');
+            }
+        }
+    }
+
+    tests
+}
+
+/// Formats the test results as a list of tests with their outcome, followed by the output of the
+/// failed tests. The output of the failed tests isn't in a code block yet
+fn format_test_results(tests: &[TestCase]) -> (String, String) {
+    let count = |outcome| tests.iter().filter(|test| test.outcome == outcome).count();
+
+    let mut summary = String::new();
+    for test in tests {
+        summary += match test.outcome {
+            TestOutcome::Passed => "✅",
+            TestOutcome::Failed => "❌",
+            TestOutcome::Ignored => "➖",
+        };
+        summary += &format!(" `{}`\n", test.name);
+    }
+    summary += &format!(
+        "**{} passed, {} failed, {} ignored**\n",
+        count(TestOutcome::Passed),
+        count(TestOutcome::Failed),
+        count(TestOutcome::Ignored)
+    );
+
+    let mut failures = String::new();
+    for test in tests.iter().filter(|test| test.outcome == TestOutcome::Failed) {
+        failures += &format!("---- {} ----\n{}\n", test.name, test.failure.trim());
+    }
+
+    (summary, failures.replace('`', "\u{200b}`"))
+}
+
+/// Run the tests in Rust code in a playground
+#[poise::command(prefix_command, track_edits, help_text_fn = "test_help", category = "Playground")]
+pub async fn test(
+    ctx: Context<'_>,
+    flags: poise::KeyValueArgs,
+    code: poise::CodeBlock,
+) -> Result<(), Error> {
+    ctx.say(stub_message(ctx)).await?;
+
+    let (flags, flag_parse_errors) = parse_flags(&ctx.data().config.get().playground, flags);
+    let code = &code.code;
+
+    let mut result = backend(ctx)
+        .execute(&PlaygroundRequest {
+            code,
+            channel: flags.channel,
+            crate_type: CrateType::Library,
+            edition: flags.edition,
+            mode: flags.mode,
+            tests: true,
+        })
+        .await?;
+
+    let tests = parse_libtest_output(&result.stdout);
+    if tests.is_empty() {
+        // The code didn't compile, or there are no tests
+        result.stderr = format_play_eval_stderr(&result.stderr, false);
+        return send_reply(ctx, result, code, &flags, &flag_parse_errors).await;
+    }
+
+    let (mut summary, failures) = format_test_results(&tests);
+    // libtest prints the result line last, so it's missing if the tests were killed, for example
+    // because of a timeout
+    if !result.stdout.contains("test result:") {
+        summary += "The tests didn't finish running\n";
+    }
+    let (text_body, text_end) = if failures.is_empty() {
+        (format!("{}{}", flag_parse_errors, summary), "")
+    } else {
+        (format!("{}{}```\n{}", flag_parse_errors, summary, failures), "```")
+    };
+    crate::helpers::reply_potentially_long_text(ctx, &text_body, text_end, async {
+        format!(
+            "Output too large. Playground link: <{}>",
+            url_from_gist(
+                &ctx.data().config.get().playground.url,
+                &flags,
+                &post_gist(ctx, code).await.unwrap_or_default()
+            ),
+        )
+    })
+    .await
+}
+
+pub fn test_help() -> String {
+    generic_help(GenericHelp {
+        command: "test",
+        desc: "Run the `#[test]` functions in the code and show which ones fail, and why",
+        mode_and_channel: true,
+        warn: false,
+        run: false,
+        example_code: "
+#[test]
+fn it_works() {
+    assert_eq!(2 + 2, 4);
+}
+",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "
+running 3 tests
+test tests::passes ... ok
+test tests::fails ... FAILED
+test tests::skipped ... ignored, not yet
+
+failures:
+
+---- tests::fails stdout ----
+thread 'tests::fails' panicked at src/lib.rs:9:9:
+assertion failed: false
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::fails
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+
+    #[test]
+    fn parses_outcomes_and_failures() {
+        let tests = parse_libtest_output(OUTPUT);
+        let outcomes =
+            tests.iter().map(|test| (test.name.as_str(), test.outcome)).collect::<Vec<_>>();
+        assert_eq!(outcomes, [
+            ("tests::passes", TestOutcome::Passed),
+            ("tests::fails", TestOutcome::Failed),
+            ("tests::skipped", TestOutcome::Ignored),
+        ]);
+        assert_eq!(
+            tests[1].failure.trim(),
+            "thread 'tests::fails' panicked at src/lib.rs:9:9:\nassertion failed: false"
+        );
+        assert!(tests[0].failure.is_empty());
+    }
+
+    #[test]
+    fn parses_doctests_and_skips_benchmarks() {
+        let output = "
+test src/lib.rs - add (line 3) ... ok
+test bench_add ... bench:          12 ns/iter (+/- 1)
+";
+        let tests = parse_libtest_output(output);
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].name, "src/lib.rs - add (line 3)");
+        assert_eq!(tests[0].outcome, TestOutcome::Passed);
+    }
+
+    #[test]
+    fn parses_nothing_without_tests() {
+        assert!(parse_libtest_output("").is_empty());
+        assert!(
+            parse_libtest_output("error[E0425]: cannot find value `x` in this scope").is_empty()
+        );
+    }
+
+    #[test]
+    fn formats_summary_and_failures() {
+        let (summary, failures) = format_test_results(&parse_libtest_output(OUTPUT));
+        assert!(summary.ends_with("**1 passed, 1 failed, 1 ignored**\n"));
+        assert!(summary.starts_with("✅ `tests::passes`\n❌ `tests::fails`\n➖ `tests::skipped`\n"));
+        assert!(failures.starts_with("---- tests::fails ----\nthread 'tests::fails' panicked"));
+    }
+}
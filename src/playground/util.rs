@@ -19,15 +19,49 @@ use super::api;
 // strings, and `is_empty` will still work.
 // So that's how (hopefully) all semantically-multiline strings in this code work
 
+/// The `[playground]` section of the config file: where code is run and flags used when a command
+/// doesn't specify them
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaygroundDefaults {
+    pub backend: super::BackendKind,
+    /// Base URL of the rust-playground instance, for example a self-hosted one
+    pub url: String,
+    /// Use the local sandbox while the playground is unreachable
+    pub fallback_to_local: bool,
+    pub local: super::SandboxConfig,
+    pub channel: api::Channel,
+    pub mode: api::Mode,
+    /// Also used by the godbolt commands unless their flags specify an edition
+    pub edition: api::Edition,
+}
+
+impl Default for PlaygroundDefaults {
+    fn default() -> Self {
+        Self {
+            backend: super::BackendKind::Playground,
+            url: "https://play.rust-lang.org".into(),
+            fallback_to_local: false,
+            local: super::SandboxConfig::default(),
+            channel: api::Channel::Nightly,
+            mode: api::Mode::Debug,
+            edition: api::Edition::E2024,
+        }
+    }
+}
+
 /// Returns the parsed flags and a String of parse errors. The parse error string will have a
 /// trailing newline (except if empty)
-pub fn parse_flags(mut args: poise::KeyValueArgs) -> (api::CommandFlags, String) {
+pub fn parse_flags(
+    defaults: &PlaygroundDefaults,
+    mut args: poise::KeyValueArgs,
+) -> (api::CommandFlags, String) {
     let mut errors = String::new();
 
     let mut flags = api::CommandFlags {
-        channel: api::Channel::Nightly,
-        mode: api::Mode::Debug,
-        edition: api::Edition::E2021,
+        channel: defaults.channel,
+        mode: defaults.mode,
+        edition: defaults.edition,
         warn: false,
         run: false,
     };
@@ -66,7 +100,7 @@ pub struct GenericHelp<'a> {
 }
 
 pub fn generic_help(spec: GenericHelp<'_>) -> String {
-    let mut reply = format!("{}. All code is executed on https://play.rust-lang.org.\n", spec.desc);
+    let mut reply = format!("{}. All code is executed on the Rust playground.\n", spec.desc);
 
     reply += "```rust\n?";
     reply += spec.command;
@@ -84,18 +118,26 @@ pub fn generic_help(spec: GenericHelp<'_>) -> String {
     reply += spec.example_code;
     reply += "``\u{200B}`\n```\n";
 
+    // The defaults of mode, channel and edition can be changed in the config file, which this
+    // static help text doesn't know about
     reply += "Optional arguments:\n";
     if spec.mode_and_channel {
-        reply += "- mode: debug, release (default: debug)\n";
-        reply += "- channel: stable, beta, nightly (default: nightly)\n";
+        reply += "- mode: debug, release\n";
+        reply += "- channel: stable, beta, nightly\n";
     }
-    reply += "- edition: 2015, 2018, 2021 (default: 2021)\n";
+    reply += &format!("- edition: {}\n", api::Edition::ALL.map(api::Edition::as_str).join(", "));
     if spec.warn {
         reply += "- warn: true, false (default: false)\n";
     }
     if spec.run {
         reply += "- run: true, false (default: false)\n";
     }
+    if spec.mode_and_channel {
+        reply +=
+            "Without arguments, the mode, channel and edition configured for the bot are used\n";
+    } else {
+        reply += "Without arguments, the edition configured for the bot is used\n";
+    }
 
     reply
 }
@@ -286,7 +328,11 @@ pub async fn send_reply(
         async {
             format!(
                 "Output too large. Playground link: <{}>",
-                api::url_from_gist(flags, &api::post_gist(ctx, code).await.unwrap_or_default()),
+                api::url_from_gist(
+                    &ctx.data().config.get().playground.url,
+                    flags,
+                    &api::post_gist(ctx, code).await.unwrap_or_default()
+                ),
             )
         },
     )
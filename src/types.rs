@@ -1,34 +1,68 @@
 use anyhow::Error;
 use poise::serenity_prelude as serenity;
 
-use crate::godbolt;
+use crate::{automod, config, cooldowns, godbolt, guild_settings, message_log};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, sqlx::FromRow)]
 pub struct ActiveSlowmode {
-    pub previous_slowmode_rate: u64,
-    pub duration: u64,
-    pub rate: u64,
-    /// The slowmode command verifies this value after the sleep and before the slowdown lift,
-    /// to make sure that no new slowmode command has been invoked since
-    pub invocation_time: chrono::DateTime<chrono::Utc>,
+    pub channel_id: i64,
+    pub previous_slowmode_rate: u16,
+    /// In minutes
+    pub duration: u32,
+    /// In seconds
+    pub rate: u16,
+    /// Unix timestamp in milliseconds. The scheduler verifies this value before the slowdown
+    /// lift, to make sure that no new slowmode command has been invoked since
+    pub invocation_time: i64,
+}
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct ActiveLockdown {
+    pub channel_id: i64,
+    pub guild_id: i64,
+    /// Permissions allowed and denied by the @everyone overwrite before the lockdown. `None` if
+    /// the channel had no such overwrite, in which case it is removed again on unlock
+    pub previous_allow: Option<i64>,
+    pub previous_deny: Option<i64>,
+    /// Unix timestamp in milliseconds. The scheduler verifies this value before the unlock, to
+    /// make sure that no new lockdown command has been invoked since
+    pub invocation_time: i64,
 }
 
 #[derive(Debug)]
 pub struct Data {
     pub bot_user_id: serenity::UserId,
-    pub discord_guild_id: serenity::GuildId,
-    #[allow(dead_code)] // might add back in
-    pub mod_role_id: serenity::RoleId,
-    pub reports_channel: Option<serenity::ChannelId>,
+    /// Settings from the config file, which may be reloaded at any time
+    pub config: config::SharedConfig,
+    /// Use [`Data::settings`] to get the settings of a guild
+    pub guild_settings: guild_settings::GuildSettingsStore,
+    /// Held per user while relaying their DM, so that quick successive DMs don't open multiple
+    /// threads
+    pub modmail_locks: std::sync::Mutex<
+        std::collections::HashMap<serenity::UserId, std::sync::Arc<tokio::sync::Mutex<()>>>,
+    >,
     pub bot_start_time: std::time::Instant,
     pub http: reqwest::Client,
+    pub database: sqlx::SqlitePool,
+    pub message_cache: std::sync::Mutex<message_log::MessageCache>,
+    pub automod: automod::Automod,
+    pub cooldowns: cooldowns::Cooldowns,
     pub godbolt_rust_targets: std::sync::Mutex<godbolt::GodboltMetadata>,
     pub godbolt_cpp_targets: std::sync::Mutex<godbolt::GodboltMetadata>,
-    pub active_slowmodes:
-        std::sync::Mutex<std::collections::HashMap<serenity::ChannelId, ActiveSlowmode>>,
+}
+
+impl Data {
+    /// Settings of the guild, like its moderator role and log channels
+    pub fn settings(
+        &self,
+        guild_id: impl Into<Option<serenity::GuildId>>,
+    ) -> guild_settings::GuildSettings {
+        self.guild_settings.resolve(&self.config.get(), guild_id.into())
+    }
 }
 
 pub type Context<'a> = poise::Context<'a, Data, Error>;
+pub type ApplicationContext<'a> = poise::ApplicationContext<'a, Data, Error>;
 
 // const EMBED_COLOR: (u8, u8, u8) = (0xf7, 0x4c, 0x00);
 pub const EMBED_COLOR: (u8, u8, u8) = (0xb7, 0x47, 0x00); // slightly less saturated